@@ -97,6 +97,7 @@ FORMATTERS = {
     "description": quotify,
     "read-write": quotify,
     "bits": quotify,
+    "values": quotify,
     "offset": (lambda value: f"0x{value:02X}" ),
 
 }
@@ -113,7 +114,7 @@ def remap_parents(pins):
                 bit['shadow'] = bit['shadow'].replace("GPIO", "GPIO.words")
     return
 
-KeepKeys = {"bits", "parent", "shadow"}
+KeepKeys = {"bits", "parent", "shadow", "values"}
 def scrub_bits(pins):
     for reg, bits in enumerate(pins):
         for key, bit in bits.items():
@@ -0,0 +1,217 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! The address space of a register map, which bytes and bits each register occupies,
+//! and the places in it that look like mistakes: fields claiming the same bits of a
+//! word, words sharing bytes, gaps between words and shadows that do not hold the bits
+//! read from them.
+//!
+//! Intentional aliases are listed by path in a top level 'aliases' entry, any register
+//! at or below one of them may share bits and bytes with others.
+//!
+//! ```yaml
+//! aliases:
+//!   - GPIO.pins
+//! ```
+//!
+use std::collections::BTreeMap;
+use yaml_rust::Yaml;
+use crate::register_map::{block_of, RegisterDef};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// worth knowing, e.g. gaps, but normal in most maps
+    Note,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// the paths of the top level 'aliases' entry
+pub fn allowed_aliases(doc: &Yaml) -> Vec<String> {
+    match &doc["aliases"] {
+        Yaml::Array(paths) => paths.iter().filter_map(|p| p.as_str()).map(|p| p.to_string()).collect(),
+        Yaml::String(p) => vec![p.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// is path at or below prefix, GPIO.pins[3].set is below GPIO.pins but GPIO.pinsets is not
+fn is_below(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['.', '[', '@']),
+        None => false,
+    }
+}
+
+/// is a on the chain of parents of b
+fn is_ancestor(defs: &[RegisterDef], a: &RegisterDef, b: &RegisterDef) -> bool {
+    let mut parent = b.parent.as_deref();
+    let mut steps = defs.len();
+    while let (Some(p), true) = (parent, steps > 0) {
+        if p == a.path() {
+            return true;
+        }
+        parent = defs.iter().find(|d| d.path() == p).and_then(|d| d.parent.as_deref());
+        steps -= 1;
+    }
+    false
+}
+
+fn bit_range(def: &RegisterDef) -> String {
+    let (hi, lo) = def.op.bits();
+    format!("{hi}:{lo}")
+}
+
+///
+/// The registers of a map by the bytes and bits they occupy
+///
+pub struct AddressSpace<'a> {
+    defs: &'a [RegisterDef],
+    /// the word registers, those that are not fields, in offset order
+    words: Vec<&'a RegisterDef>,
+    aliases: Vec<String>,
+}
+
+impl<'a> AddressSpace<'a> {
+    pub fn new(defs: &'a [RegisterDef], aliases: Vec<String>) -> AddressSpace<'a> {
+        let mut words: Vec<&RegisterDef> = defs.iter().filter(|d| !d.is_field()).collect();
+        words.sort_by_key(|d| d.op.offset);
+        AddressSpace { defs, words, aliases }
+    }
+
+    fn allowed(&self, paths: &[&str]) -> bool {
+        paths.iter().any(|p| self.aliases.iter().any(|a| is_below(p, a)))
+    }
+
+    /// everything found, fields first, then words, gaps and shadows
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = Vec::new();
+        self.overlapping_fields(&mut findings);
+        self.aliased_words(&mut findings);
+        self.gaps(&mut findings);
+        self.shadows(&mut findings);
+        findings
+    }
+
+    ///
+    /// Fields of the same word claiming the same bits, other than a field and those
+    /// nested in it
+    ///
+    fn overlapping_fields(&self, findings: &mut Vec<Finding>) {
+        let mut by_word: BTreeMap<&str, Vec<&RegisterDef>> = BTreeMap::new();
+        for def in self.defs.iter().filter(|d| d.is_field()) {
+            by_word.entry(def.word.as_str()).or_default().push(def);
+        }
+        for (word, fields) in by_word {
+            for (i, a) in fields.iter().enumerate() {
+                for b in &fields[i + 1..] {
+                    if a.op.read_mask & b.op.read_mask == 0 || self.allowed(&[a.path(), b.path()])
+                        || is_ancestor(self.defs, a, b) || is_ancestor(self.defs, b, a) {
+                        continue;
+                    }
+                    findings.push(Finding {
+                        severity: Severity::Warning,
+                        message: format!("bits {} of {} overlap bits {} of {} in {word}",
+                                         bit_range(a), a.path(), bit_range(b), b.path()),
+                    });
+                }
+            }
+        }
+    }
+
+    ///
+    /// Words sharing an offset, or any of their bytes
+    ///
+    fn aliased_words(&self, findings: &mut Vec<Finding>) {
+        for (i, a) in self.words.iter().enumerate() {
+            let a_end = a.op.offset + a.op.width.bytes();
+            for b in self.words[i + 1..].iter().take_while(|b| b.op.offset < a_end) {
+                if self.allowed(&[a.path(), b.path()]) {
+                    continue;
+                }
+                let message = if a.op.offset == b.op.offset && a.op.width == b.op.width {
+                    format!("{} and {} share offset 0x{:X}", a.path(), b.path(), a.op.offset)
+                } else {
+                    let b_end = b.op.offset + b.op.width.bytes();
+                    format!("bytes 0x{:X}-0x{:X} of {} overlap {}", b.op.offset, a_end.min(b_end) - 1, a.path(), b.path())
+                };
+                findings.push(Finding { severity: Severity::Warning, message });
+            }
+        }
+    }
+
+    ///
+    /// Unused bytes between the words of each top level block
+    ///
+    fn gaps(&self, findings: &mut Vec<Finding>) {
+        let mut ends: BTreeMap<&str, (u64, &str)> = BTreeMap::new();
+        for word in &self.words {
+            let block = block_of(word.path());
+            let end = word.op.offset + word.op.width.bytes();
+            if let Some((last_end, last)) = ends.get(block) {
+                if word.op.offset > *last_end {
+                    findings.push(Finding {
+                        severity: Severity::Note,
+                        message: format!("gap 0x{:X}-0x{:X} in {block} between {last} and {}",
+                                         last_end, word.op.offset - 1, word.path()),
+                    });
+                } else if end <= *last_end {
+                    continue;
+                }
+            }
+            ends.insert(block, (end, word.path()));
+        }
+    }
+
+    ///
+    /// Shadows, which are read in place of the register, that are a different width or
+    /// do not cover the register's bits
+    ///
+    fn shadows(&self, findings: &mut Vec<Finding>) {
+        for def in self.defs {
+            let Some(shadow_path) = &def.shadow else { continue };
+            let Some(shadow) = self.defs.iter().find(|d| d.path() == shadow_path) else { continue };
+
+            if shadow.op.width != def.op.width {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    message: format!("shadow {} of {} is {} bits wide, {} is {} bits", shadow_path, def.path(),
+                                     shadow.op.width.bits(), def.path(), def.op.width.bits()),
+                });
+            } else if shadow.op.read_mask & def.op.read_mask != def.op.read_mask {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    message: format!("shadow {} of {} does not hold its bits {}, only {}", shadow_path, def.path(),
+                                     bit_range(def), bit_range(shadow)),
+                });
+            }
+        }
+    }
+}
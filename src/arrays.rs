@@ -0,0 +1,297 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Arrays of registers generated from one element, so that a list such as a GPIO
+//! block's pins needn't be written out pin by pin.
+//!
+//! ```yaml
+//! pins:
+//!   array:
+//!     count: 58
+//!     element:
+//!       function: {bits: "2:0", stride: 3, per-word: 10, parent: "GPIO.words.function{word}"}
+//!       set: {bits: "0:0", stride: 1, parent: "GPIO.words.set{word}"}
+//!       event: {offset: "{0x40 + 4 * i}"}
+//! ```
+//!
+//! expands, when the file is loaded, into a list of count copies of element.  In each
+//! copy `{expression}` in a string is replaced by its value for the element's index `i`,
+//! a string that is only an expression becoming a number.  A field with a 'stride' of
+//! bits has its 'bits' moved up by that much for each element, 'per-word' elements to a
+//! word(32 bits / stride by default), and may use `word`, the index of its word, and
+//! `slot`, its place within it, in expressions.  Expressions are integer arithmetic with
+//! + - * / % and parentheses.
+//!
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+use crate::value::parse_value;
+
+/// Entries of a field that place it in an array, removed once they are applied
+const STRIDE: &str = "stride";
+const PER_WORD: &str = "per-word";
+
+/// the variables an expression may use
+struct Index {
+    i: u64,
+    /// the word and slot within it of a field with a stride
+    word: Option<(u64, u64)>,
+}
+
+/// is node an array to generate, {array: {count: .., element: ..}}
+fn is_array(node: &Yaml) -> bool {
+    match node {
+        Yaml::Hash(h) => h.len() == 1 && !node["array"]["count"].is_badvalue(),
+        _ => false,
+    }
+}
+
+/// does the document have any arrays to generate
+pub fn has_arrays(node: &Yaml) -> bool {
+    match node {
+        Yaml::Hash(h) => is_array(node) || h.values().any(has_arrays),
+        Yaml::Array(a) => a.iter().any(has_arrays),
+        _ => false,
+    }
+}
+
+///
+/// Replace every array in node with the list it generates.  path locates node for
+/// errors.
+///
+pub fn expand_arrays(node: Yaml, path: &str) -> Result<Yaml, String> {
+    if is_array(&node) {
+        return generate(&node["array"], path);
+    }
+    match node {
+        Yaml::Hash(hash) => {
+            let mut expanded = Hash::new();
+            for (key, value) in hash {
+                let child = match key.as_str() {
+                    Some(k) if path.is_empty() => k.to_string(),
+                    Some(k) => format!("{path}.{k}"),
+                    None => path.to_string(),
+                };
+                expanded.insert(key, expand_arrays(value, &child)?);
+            }
+            Ok(Yaml::Hash(expanded))
+        }
+        Yaml::Array(array) => array.into_iter().enumerate()
+            .map(|(i, v)| expand_arrays(v, &format!("{path}[{i}]")))
+            .collect::<Result<Vec<Yaml>, String>>()
+            .map(Yaml::Array),
+        other => Ok(other),
+    }
+}
+
+/// the list an array's count and element generate
+fn generate(array: &Yaml, path: &str) -> Result<Yaml, String> {
+    let count = match &array["count"] {
+        Yaml::Integer(c) if *c >= 0 => *c as u64,
+        _ => return Err(format!("array count of {path} must be a number")),
+    };
+    if array["element"].is_badvalue() {
+        return Err(format!("array {path} has no element"));
+    }
+    let mut elements: Vec<Yaml> = Vec::new();
+    for i in 0..count {
+        let element_path = format!("{path}[{i}]");
+        let element = instantiate(&array["element"], &Index { i, word: None }, &element_path)?;
+        // arrays within the element are generated with their own index
+        elements.push(expand_arrays(element, &element_path)?);
+    }
+    Ok(Yaml::Array(elements))
+}
+
+/// a copy of node for index, with its expressions evaluated and strides applied
+fn instantiate(node: &Yaml, index: &Index, path: &str) -> Result<Yaml, String> {
+    match node {
+        _ if is_array(node) => Ok(node.clone()),
+        Yaml::Hash(hash) => {
+            let stride = match &node[STRIDE] {
+                Yaml::BadValue => None,
+                Yaml::Integer(s) if *s > 0 => Some(*s as u64),
+                _ => return Err(format!("stride of {path} must be a number of bits")),
+            };
+            let index = match stride {
+                None => Index { i: index.i, word: index.word },
+                Some(s) => {
+                    let per_word = match &node[PER_WORD] {
+                        Yaml::BadValue => (32 / s).max(1),
+                        Yaml::Integer(p) if *p > 0 => *p as u64,
+                        _ => return Err(format!("per-word of {path} must be a number")),
+                    };
+                    Index { i: index.i, word: Some((index.i / per_word, index.i % per_word)) }
+                }
+            };
+            let mut copy = Hash::new();
+            for (key, value) in hash {
+                let name = key.as_str().unwrap_or("");
+                if stride.is_some() && (name == STRIDE || name == PER_WORD) {
+                    continue;
+                }
+                let child = format!("{path}.{name}");
+                let mut value = instantiate(value, &index, &child)?;
+                if let (Some(s), "bits", Some((_, slot))) = (stride, name, index.word) {
+                    value = Yaml::String(shift_bits(&value, s * slot).map_err(|e| format!("bits of {path}: {e}"))?);
+                }
+                copy.insert(key.clone(), value);
+            }
+            Ok(Yaml::Hash(copy))
+        }
+        Yaml::Array(array) => array.iter().enumerate()
+            .map(|(n, v)| instantiate(v, index, &format!("{path}[{n}]")))
+            .collect::<Result<Vec<Yaml>, String>>()
+            .map(Yaml::Array),
+        Yaml::String(text) => substitute(text, index).map_err(|e| format!("{path}: {e}")),
+        other => Ok(other.clone()),
+    }
+}
+
+/// "hi:lo" moved up by shift bits
+fn shift_bits(bits: &Yaml, shift: u64) -> Result<String, String> {
+    let text = match bits {
+        Yaml::String(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        _ => return Err("expected hi:lo".to_string()),
+    };
+    let mut ends: Vec<String> = Vec::new();
+    for end in text.split(':') {
+        ends.push((parse_value(end)? + shift).to_string());
+    }
+    Ok(ends.join(":"))
+}
+
+/// text with each {expression} replaced by its value, a number if text is just one
+fn substitute(text: &str, index: &Index) -> Result<Yaml, String> {
+    let trimmed = text.trim();
+    if trimmed.starts_with('{') && trimmed.ends_with('}') && trimmed[1..].find(['{', '}']) == Some(trimmed.len() - 2) {
+        return Ok(Yaml::Integer(evaluate(&trimmed[1..trimmed.len() - 1], index)? as i64));
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or(format!("unclosed {{ in \"{text}\""))? + start;
+        out.push_str(&rest[..start]);
+        out.push_str(&evaluate(&rest[start + 1..end], index)?.to_string());
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(Yaml::String(out))
+}
+
+///
+/// Evaluate an integer expression of numbers, the index variables, + - * / % and
+/// parentheses
+///
+fn evaluate(expression: &str, index: &Index) -> Result<u64, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens: &tokens, position: 0, index };
+    let value = parser.sum()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(value),
+        Some(t) => Err(format!("unexpected '{t}' in {{{expression}}}")),
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>, String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/%()".contains(c) {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else {
+            return Err(format!("unexpected '{c}' in {{{expression}}}"));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    index: &'a Index,
+}
+
+impl Parser<'_> {
+    fn next_if(&mut self, ops: &[&str]) -> Option<String> {
+        let token = self.tokens.get(self.position).filter(|t| ops.contains(&t.as_str()))?.clone();
+        self.position += 1;
+        Some(token)
+    }
+
+    /// terms separated by + and -
+    fn sum(&mut self) -> Result<u64, String> {
+        let mut value = self.product()?;
+        while let Some(op) = self.next_if(&["+", "-"]) {
+            let rhs = self.product()?;
+            value = if op == "+" { value.checked_add(rhs) } else { value.checked_sub(rhs) }
+                .ok_or(format!("{value} {op} {rhs} is out of range"))?;
+        }
+        Ok(value)
+    }
+
+    /// factors separated by *, / and %
+    fn product(&mut self) -> Result<u64, String> {
+        let mut value = self.factor()?;
+        while let Some(op) = self.next_if(&["*", "/", "%"]) {
+            let rhs = self.factor()?;
+            value = match op.as_str() {
+                "*" => value.checked_mul(rhs),
+                "/" => value.checked_div(rhs),
+                _ => value.checked_rem(rhs),
+            }.ok_or(format!("{value} {op} {rhs} is out of range"))?;
+        }
+        Ok(value)
+    }
+
+    /// a number, variable or parenthesised sum
+    fn factor(&mut self) -> Result<u64, String> {
+        let token = self.tokens.get(self.position).ok_or("expression ends early")?.clone();
+        self.position += 1;
+        match token.as_str() {
+            "(" => {
+                let value = self.sum()?;
+                self.next_if(&[")"]).ok_or("missing )")?;
+                Ok(value)
+            }
+            "i" => Ok(self.index.i),
+            "word" | "slot" => match self.index.word {
+                Some((word, slot)) => Ok(if token == "word" { word } else { slot }),
+                None => Err(format!("{token} needs a stride")),
+            },
+            t if t.starts_with(|c: char| c.is_ascii_digit()) => parse_value(t),
+            t => Err(format!("unknown '{t}'")),
+        }
+    }
+}
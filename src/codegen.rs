@@ -0,0 +1,394 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Source code generated from a register map, so that firmware and drivers use the
+//! offsets, masks and values of the same file the tool reads.
+//!
+use std::collections::HashSet;
+use std::path::Path;
+use crate::compose::composed_files;
+use crate::config::load_config;
+use crate::register_map::{identifier, RegionKeys, RegisterDef, RegisterMap};
+use crate::register_op::RegisterAccess;
+
+/// a C integer constant, unsigned and wide enough for the value
+fn c_const(value: u64, hex: bool) -> String {
+    let suffix = if value > u32::MAX as u64 { "ull" } else { "u" };
+    if hex {
+        format!("0x{value:X}{suffix}")
+    } else {
+        format!("{value}{suffix}")
+    }
+}
+
+/// text safe to place in a C comment
+fn c_comment(text: &str) -> String {
+    text.replace("*/", "* /").replace('\n', " ")
+}
+
+fn c_type(bytes: u64) -> &'static str {
+    match bytes {
+        1 => "uint8_t",
+        2 => "uint16_t",
+        8 => "uint64_t",
+        _ => "uint32_t",
+    }
+}
+
+///
+/// Collects #defines, failing on any identifier that two paths sanitize to
+///
+struct Defines {
+    out: String,
+    seen: HashSet<String>,
+    errs: Vec<String>,
+}
+
+impl Defines {
+    fn define(&mut self, name: &str, value: String, path: &str) {
+        if !self.seen.insert(name.to_string()) {
+            self.errs.push(format!("{path}: identifier {name} is already defined"));
+        }
+        self.out.push_str(&format!("#define {name} {value}\n"));
+    }
+
+    fn register(&mut self, def: &RegisterDef) {
+        let path = def.path();
+        let name = identifier(path).to_uppercase();
+        let (hi, lo) = def.op.bits();
+
+        self.out.push_str(&format!("\n/* {path} [{hi}:{lo}] {}", def.op.access_type().as_str()));
+        if let Some(d) = &def.description {
+            self.out.push_str(&format!(": {}", c_comment(d)));
+        }
+        self.out.push_str(" */\n");
+        if def.is_field() {
+            self.out.push_str(&format!("/* field of {} */\n", def.word));
+        }
+        self.define(&format!("{name}_OFFSET"), c_const(def.op.offset, true), path);
+        self.define(&format!("{name}_SIZE"), c_const(def.op.width.bits() as u64, false), path);
+        self.define(&format!("{name}_SHIFT"), c_const(def.op.shift as u64, false), path);
+        self.define(&format!("{name}_WIDTH"), c_const(def.op.field_width() as u64, false), path);
+        self.define(&format!("{name}_MASK"), c_const(def.op.read_mask, true), path);
+        if let Some(reset) = def.reset {
+            self.define(&format!("{name}_RESET"), c_const(reset, true), path);
+        }
+        for (label, value) in &def.op.values {
+            self.define(&format!("{name}_VAL_{}", identifier(label).to_uppercase()), c_const(*value, true), path);
+        }
+    }
+}
+
+///
+/// A struct laying out a block's word registers at their offsets, padded between them
+///
+fn c_struct(map: &RegisterMap, block: &str, out: &mut String) {
+    let mut words: Vec<&RegisterDef> = map.words().filter(|r| map.block(r.path()) == block).collect();
+    words.sort_by_key(|r| r.op.offset);
+    let (start, end) = map.block_span(block);
+    let type_name = format!("{}_regs_t", identifier(block).to_lowercase());
+
+    out.push_str(&format!("\n/* {block} registers, at {}_BLOCK_OFFSET from the base */\n", identifier(block).to_uppercase()));
+    out.push_str(&format!("#define {}_BLOCK_OFFSET {}\n", identifier(block).to_uppercase(), c_const(start, true)));
+    out.push_str("typedef struct __attribute__((packed)) {\n");
+    let mut position = start;
+    for word in words {
+        let member = identifier(word.path().strip_prefix(block).unwrap_or(word.path())).to_lowercase();
+        let word_end = word.op.offset + word.op.width.bytes();
+        if word.op.offset < position {
+            out.push_str(&format!("    /* {member} at 0x{:X} overlaps the register before it */\n", word.op.offset - start));
+            // the bytes it has past the one before still count toward the block's size
+            if word_end > position {
+                out.push_str(&format!("    uint8_t reserved_0x{:X}[{}];\n", position - start, word_end - position));
+                position = word_end;
+            }
+            continue;
+        }
+        if word.op.offset > position {
+            out.push_str(&format!("    uint8_t reserved_0x{:X}[{}];\n", position - start, word.op.offset - position));
+        }
+        out.push_str(&format!("    volatile {} {member};\n", c_type(word.op.width.bytes())));
+        position = word_end;
+    }
+    out.push_str(&format!("}} {type_name};\n"));
+    out.push_str(&format!("_Static_assert(sizeof({type_name}) == 0x{:X}, \"{type_name} layout\");\n", end - start));
+}
+
+///
+/// A C header of #defines for every register and field of the map
+///
+/// Identifiers are the upper cased path, so GPIO.pins[27].function gives
+/// GPIO_PINS_27_FUNCTION_OFFSET, _SIZE(bits of the access), _SHIFT, _WIDTH,
+/// _MASK(in place), _RESET when known and one constant per symbolic value, unshifted.
+/// Offsets are from the base of the register's block, the file's base unless the block
+/// gives its own as <BLOCK>_BASE and <BLOCK>_LENGTH.  With structs each block
+/// also gets a packed struct of its word registers.
+///
+pub fn c_header(map: &RegisterMap, name: &str, structs: bool) -> Result<String, Vec<String>> {
+    let guard = format!("{}_REGISTERS_H", identifier(name).to_uppercase());
+    let prefix = identifier(name).to_uppercase();
+    let mut defines = Defines { out: String::new(), seen: HashSet::new(), errs: Vec::new() };
+
+    defines.out.push_str(&format!("/* Generated by register_tool from {name}, do not edit */\n"));
+    defines.out.push_str(&format!("#ifndef {guard}\n#define {guard}\n\n#include <stdint.h>\n\n"));
+    if !map.description.is_empty() {
+        defines.out.push_str(&format!("/* {} */\n", c_comment(&map.description)));
+    }
+    if let Some(region) = map.file_region() {
+        defines.define(&format!("{prefix}_BASE"), c_const(region.base, true), name);
+        defines.define(&format!("{prefix}_LENGTH"), c_const(region.length, true), name);
+    }
+    for (block, region) in &map.regions {
+        let block_prefix = identifier(block).to_uppercase();
+        defines.define(&format!("{block_prefix}_BASE"), c_const(region.base, true), block);
+        defines.define(&format!("{block_prefix}_LENGTH"), c_const(region.length, true), block);
+    }
+
+    for def in &map.registers {
+        defines.register(def);
+    }
+
+    if structs {
+        for block in map.blocks() {
+            if map.words().any(|r| map.block(r.path()) == block) {
+                c_struct(map, block, &mut defines.out);
+            }
+        }
+    }
+    defines.out.push_str(&format!("\n#endif /* {guard} */\n"));
+
+    if !defines.errs.is_empty() {
+        return Err(defines.errs);
+    }
+    Ok(defines.out)
+}
+
+const RUST_KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+
+/// an identifier that is not a Rust keyword
+fn rust_identifier(text: &str) -> String {
+    let name = identifier(text);
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn rust_access(access: RegisterAccess) -> &'static str {
+    match access {
+        RegisterAccess::ReadOnly => "ReadOnly",
+        RegisterAccess::ReadWrite => "ReadWrite",
+        RegisterAccess::WriteOnly => "WriteOnly",
+        RegisterAccess::Write1Clear => "Write1Clear",
+        RegisterAccess::Write1Set => "Write1Set",
+        RegisterAccess::ReadClear => "ReadClear",
+        RegisterAccess::WriteClear => "WriteClear",
+        RegisterAccess::WriteSet => "WriteSet",
+        RegisterAccess::Unspecified => "Unspecified",
+    }
+}
+
+///
+/// A register's type, its Register impl and, when it has 'values', the enum of them
+///
+fn rust_register(def: &RegisterDef, block: &str, names: &mut HashSet<String>, errs: &mut Vec<String>, out: &mut String) {
+    let path = def.path();
+    let name = identifier(path).to_uppercase();
+    let value_type = format!("{name}_VALUE");
+    for n in [&name, &value_type] {
+        if !names.insert(n.clone()) {
+            errs.push(format!("{path}: identifier {n} is already defined"));
+        }
+    }
+    let op = &def.op;
+
+    out.push_str(&format!("\n    /// {path}"));
+    if let Some(d) = &def.description {
+        out.push_str(&format!(": {}", d.replace('\n', " ")));
+    }
+    out.push_str(&format!("\n    pub struct {name};\n\n    impl Register for {name} {{\n"));
+    out.push_str(&format!("        type Block = {block};\n"));
+    out.push_str(&format!("        const PATH: &'static str = {path:?};\n"));
+    out.push_str(&format!("        const OFFSET: u64 = 0x{:X};\n", op.offset));
+    out.push_str(&format!("        const WIDTH: AccessWidth = AccessWidth::Bits{};\n", op.width.bits()));
+    out.push_str(&format!("        const SHIFT: u32 = {};\n", op.shift));
+    out.push_str(&format!("        const MASK: u64 = 0x{:X};\n", op.read_mask));
+    out.push_str(&format!("        const ACCESS: RegisterAccess = RegisterAccess::{};\n", rust_access(op.access_type())));
+    match op.shadow_offset() {
+        Some(s) => out.push_str(&format!("        const SHADOW: Option<u64> = Some(0x{s:X});\n")),
+        None => out.push_str("        const SHADOW: Option<u64> = None;\n"),
+    }
+    if op.values.is_empty() {
+        out.push_str(&format!("        type Value = Bits<{}>;\n    }}\n", op.field_width()));
+    } else {
+        out.push_str(&format!("        type Value = {value_type};\n    }}\n"));
+    }
+    // reading an rc register clears it, so it is only read by asking for that
+    if op.shadow_offset().is_some() || (op.access_type().is_readable() && op.access_type() != RegisterAccess::ReadClear) {
+        out.push_str(&format!("\n    impl Readable for {name} {{}}\n"));
+    } else if op.access_type() == RegisterAccess::ReadClear {
+        out.push_str(&format!("\n    impl ReadClearable for {name} {{}}\n"));
+    }
+    if op.access_type().is_writable() {
+        out.push_str(&format!("\n    impl Writable for {name} {{}}\n"));
+    }
+    if op.values.is_empty() {
+        return;
+    }
+
+    let mut variants: Vec<(String, u64)> = Vec::new();
+    for (label, value) in &op.values {
+        let variant = rust_identifier(label);
+        if variants.iter().any(|(v, _)| *v == variant) {
+            errs.push(format!("{path}: value {label} is already defined as {variant}"));
+        }
+        if let Some((other, _)) = variants.iter().find(|(_, v)| v == value) {
+            errs.push(format!("{path}: value {label}={value} is the same as {other}, an enum cannot give both"));
+        }
+        if *value > op.field_max() {
+            errs.push(format!("{path}: value {label}={value} does not fit in {} bits", op.field_width()));
+        }
+        variants.push((variant, *value));
+    }
+
+    out.push_str(&format!("\n    #[derive(Debug, Clone, Copy, PartialEq, Eq)]\n    pub enum {value_type} {{\n"));
+    for (variant, _) in &variants {
+        out.push_str(&format!("        {variant},\n"));
+    }
+    out.push_str(&format!("    }}\n\n    impl FieldValue for {value_type} {{\n        fn to_bits(self) -> u64 {{\n            match self {{\n"));
+    for (variant, value) in &variants {
+        out.push_str(&format!("                {value_type}::{variant} => 0x{value:X},\n"));
+    }
+    out.push_str("            }\n        }\n\n        fn from_bits(bits: u64) -> Result<Self, String> {\n            match bits {\n");
+    for (variant, value) in &variants {
+        out.push_str(&format!("                0x{value:X} => Ok({value_type}::{variant}),\n"));
+    }
+    out.push_str(&format!("                _ => Err(format!(\"{{}} (0x{{:X}}) is not a value of {path}\", bits, bits)),\n"));
+    out.push_str("            }\n        }\n    }\n");
+}
+
+///
+/// A Rust module of typed registers, see the pac module, for every register of the map
+///
+/// The module is named after the register file and each register is a type named as
+/// in c_header, GPIO.pins[27].function becoming GPIO_PINS_27_FUNCTION with its values,
+/// if any, in the enum GPIO_PINS_27_FUNCTION_VALUE.  Each block is a type, GPIO_BLOCK,
+/// that its registers name as theirs.  krate is the path the register_tool library is
+/// used by, "register_tool" outside this crate.
+///
+pub fn rust_module(map: &RegisterMap, name: &str, krate: &str) -> Result<String, Vec<String>> {
+    let mut out = String::new();
+    let mut names: HashSet<String> = HashSet::new();
+    let mut errs: Vec<String> = Vec::new();
+
+    if let Some(region) = map.file_region() {
+        out.push_str(&format!("    pub const DEVICE: &str = {:?};\n", region.device));
+        out.push_str(&format!("    pub const BASE: u64 = 0x{:X};\n", region.base));
+        out.push_str(&format!("    pub const LENGTH: u64 = 0x{:X};\n", region.length));
+    }
+
+    // each block is a type, so a register can only be used with the memory of its own
+    for block in map.blocks() {
+        let block_type = format!("{}_BLOCK", identifier(block).to_uppercase());
+        if !names.insert(block_type.clone()) {
+            errs.push(format!("{block}: identifier {block_type} is already defined"));
+        }
+        let region = match map.regions.iter().find(|(b, _)| b == block) {
+            Some((_, region)) => region.clone(),
+            None => match map.file.region(&RegionKeys::default(), block) {
+                Ok(region) => region,
+                Err(e) => {
+                    errs.push(e);
+                    continue;
+                }
+            },
+        };
+        out.push_str(&format!("\n    /// {block}\n    pub struct {block_type};\n\n    impl Block for {block_type} {{\n"));
+        out.push_str(&format!("        const DEVICE: &'static str = {:?};\n", region.device));
+        out.push_str(&format!("        const BASE: u64 = 0x{:X};\n", region.base));
+        out.push_str(&format!("        const LENGTH: u64 = 0x{:X};\n    }}\n", region.length));
+    }
+
+    for def in &map.registers {
+        let block = format!("{}_BLOCK", identifier(map.block(&def.word)).to_uppercase());
+        rust_register(def, &block, &mut names, &mut errs, &mut out);
+    }
+    out.push_str("}\n");
+
+    // only what the registers use is imported, so the module compiles without warnings
+    let pac: Vec<&str> = [("Bits", "Bits<"), ("Block", "impl Block for"), ("FieldValue", "impl FieldValue for"),
+                          ("ReadClearable", "impl ReadClearable for"), ("Readable", "impl Readable for"),
+                          ("Register", "impl Register for"), ("Writable", "impl Writable for")]
+        .iter().filter(|(_, used)| out.contains(used)).map(|(item, _)| *item).collect();
+    let mut head = format!("// Generated by register_tool from {name}, do not edit\n");
+    head.push_str("#[allow(non_camel_case_types, dead_code)]\n");
+    head.push_str(&format!("pub mod {} {{\n", rust_identifier(&name.to_lowercase())));
+    if !pac.is_empty() {
+        head.push_str(&format!("    use {krate}::pac::{{{}}};\n", pac.join(", ")));
+    }
+    if out.contains("impl Register for") {
+        head.push_str(&format!("    use {krate}::register_op::{{AccessWidth, RegisterAccess}};\n"));
+    }
+    head.push('\n');
+    out.insert_str(0, &head);
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+    Ok(out)
+}
+
+///
+/// Write the Rust module for a register file to out, for use from build.rs
+///
+/// ```ignore
+/// // build.rs
+/// let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("registers.rs");
+/// register_tool::codegen::generate_rust("raspberrypi4b.yaml", &out).unwrap();
+///
+/// // src/main.rs
+/// include!(concat!(env!("OUT_DIR"), "/registers.rs"));
+/// use raspberrypi4b::*;
+/// ```
+///
+/// Cargo is told to rerun the build script when the register file, or any file it
+/// includes or imports, changes.
+///
+pub fn generate_rust(config: &str, out: &Path) -> Result<(), Vec<String>> {
+    println!("cargo:rerun-if-changed={config}");
+    for file in composed_files(config).map_err(|e| vec![e])? {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+    let (doc, mut descender) = load_config(config).map_err(|e| vec![e])?;
+    let map = RegisterMap::new(&doc, &mut *descender, "")?;
+    let name = Path::new(config).file_stem().and_then(|s| s.to_str()).unwrap_or("registers");
+    let source = rust_module(&map, name, "register_tool")?;
+    std::fs::write(out, source).map_err(|e| vec![format!("{}: {}", out.display(), e)])
+}
@@ -0,0 +1,253 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Register files composed of others.  A file may name files to include, whose
+//! entries it takes as its own, and files to import under a namespace, whose
+//! registers it mounts below its root, so that a board file can use an SoC's file and
+//! add aliases of its own.
+//!
+//! ```yaml
+//! include: common.yaml
+//! import:
+//!   soc: bcm2711.yaml
+//! registers:
+//!   LED_STATUS: {parent: soc.GPIO.words.set0, bits: "27:27"}
+//! ```
+//!
+//! Paths are relative to the file naming them.  The file's own entries override those
+//! it includes or imports, maps being merged and anything else replaced whole.
+//!
+use std::path::{Path, PathBuf};
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+use crate::config::load_document;
+use crate::address_space::allowed_aliases;
+use crate::register_map::{document_root, find_node};
+
+/// The keys that compose a file of others, removed once they are resolved
+const DIRECTIVES: [&str; 2] = ["include", "import"];
+
+/// Keys whose values are paths from the root, to be moved with an imported file
+const PATH_ENTRIES: [&str; 2] = ["parent", "shadow"];
+
+/// Keys that give the memory of a block, or of an imported file at its top
+pub(crate) const REGION_ENTRIES: [&str; 3] = ["device", "base", "length"];
+
+/// does the document name any other files
+pub fn has_directives(doc: &Yaml) -> bool {
+    DIRECTIVES.iter().any(|d| !doc[*d].is_badvalue())
+}
+
+/// the chain of files leading to path, "board.yaml -> bcm2711.yaml"
+fn describe(chain: &[PathBuf], path: &Path) -> String {
+    chain.iter().map(|p| p.display().to_string())
+        .chain(std::iter::once(path.display().to_string()))
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+///
+/// Load the register file at path with everything it includes and imports in place
+///
+pub fn load_composed(path: &str) -> Result<Yaml, String> {
+    compose(Path::new(path), &mut Vec::new(), &mut Vec::new())
+}
+
+///
+/// The files path includes and imports, directly or through others, for build scripts
+/// to watch
+///
+pub fn composed_files(path: &str) -> Result<Vec<PathBuf>, String> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    compose(Path::new(path), &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+///
+/// Load path, composing it of the files it names.  chain is the files including it,
+/// to detect cycles and name them in errors, and files every other file loaded.
+///
+fn compose(path: &Path, chain: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) -> Result<Yaml, String> {
+    let here = describe(chain, path);
+    let canonical = path.canonicalize().map_err(|e| format!("{here}: {e}"))?;
+    if chain.iter().any(|p| p.canonicalize().is_ok_and(|c| c == canonical)) {
+        return Err(format!("include cycle: {here}"));
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{here}: {e}"))?;
+    if !chain.is_empty() && !files.contains(&path.to_path_buf()) {
+        files.push(path.to_path_buf());
+    }
+    let doc = load_document(&content).map_err(|e| format!("{here}: {e}"))?;
+    if !has_directives(&doc) {
+        return Ok(doc);
+    }
+    let Yaml::Hash(own) = doc else {
+        return Ok(doc);
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    chain.push(path.to_path_buf());
+    let result = compose_hash(own, dir, chain, files, &here);
+    chain.pop();
+    result
+}
+
+/// the file whose entries are own, less its include and import, over those it names
+fn compose_hash(mut own: Hash, dir: &Path, chain: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>, here: &str) -> Result<Yaml, String> {
+    let includes = own.remove(&Yaml::String("include".into())).unwrap_or(Yaml::BadValue);
+    let imports = own.remove(&Yaml::String("import".into())).unwrap_or(Yaml::BadValue);
+
+    /*
+     * included files first, in order, then the file's own entries over them
+     */
+    let mut composed = Yaml::Hash(Hash::new());
+    let include_paths = match &includes {
+        Yaml::BadValue => Vec::new(),
+        Yaml::String(p) => vec![p.as_str()],
+        Yaml::Array(a) => a.iter().map(|p| p.as_str().ok_or(format!("{here}: include entries must be paths")))
+            .collect::<Result<Vec<&str>, String>>()?,
+        _ => return Err(format!("{here}: include must be a path or a list of paths")),
+    };
+    for include in include_paths {
+        let included = compose(&dir.join(include), chain, files)?;
+        merge(&mut composed, included);
+    }
+    merge(&mut composed, Yaml::Hash(own));
+
+    /*
+     * then imports, under the root the file ends up with, again beneath its own entries
+     */
+    let imports = match imports {
+        Yaml::BadValue => Hash::new(),
+        Yaml::Hash(h) => h,
+        _ => return Err(format!("{here}: import must map namespaces to paths")),
+    };
+    let root = document_root(&composed);
+    for (namespace, import) in imports {
+        let (Some(namespace), Some(import)) = (namespace.as_str(), import.as_str()) else {
+            return Err(format!("{here}: import must map namespaces to paths"));
+        };
+        if namespace.is_empty() || namespace.contains(['.', '[', '@']) {
+            return Err(format!("{here}: invalid import namespace '{namespace}'"));
+        }
+        let import_path = dir.join(import);
+        let imported = compose(&import_path, chain, files)?;
+        mount(&mut composed, &root, namespace, imported)
+            .map_err(|e| format!("{here} -> {}: {e}", import_path.display()))?;
+    }
+    Ok(composed)
+}
+
+///
+/// Merge over into into, maps key by key and anything else replacing what was there
+///
+fn merge(into: &mut Yaml, over: Yaml) {
+    match (into, over) {
+        (Yaml::Hash(into), Yaml::Hash(over)) => {
+            for (key, value) in over {
+                match into.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        into.insert(key, value);
+                    }
+                }
+            }
+        }
+        (into, over) => *into = over,
+    }
+}
+
+/// prefix every parent and shadow below node with namespace, to keep them pointing into it
+fn move_paths(node: &mut Yaml, namespace: &str) {
+    match node {
+        Yaml::Hash(hash) => {
+            for (key, value) in hash.iter_mut() {
+                match (key.as_str(), &value) {
+                    (Some(k), Yaml::String(p)) if PATH_ENTRIES.contains(&k) => *value = Yaml::String(format!("{namespace}.{p}")),
+                    _ => move_paths(value, namespace),
+                }
+            }
+        }
+        Yaml::Array(array) => array.iter_mut().for_each(|v| move_paths(v, namespace)),
+        _ => {}
+    }
+}
+
+///
+/// Put the registers of imported, and its memory and aliases, below root at namespace
+/// with anything composed already holds there over them
+///
+fn mount(composed: &mut Yaml, root: &str, namespace: &str, imported: Yaml) -> Result<(), String> {
+    let imported_root = document_root(&imported);
+    let mut registers = find_node(&imported, &imported_root)
+        .cloned()
+        .ok_or(format!("root {imported_root} not found"))?;
+    if !matches!(registers, Yaml::Hash(_)) {
+        return Err(format!("root {imported_root} holds no registers"));
+    }
+    move_paths(&mut registers, namespace);
+    if let Yaml::Hash(hash) = &mut registers {
+        for key in REGION_ENTRIES {
+            if !imported[key].is_badvalue() {
+                hash.insert(Yaml::String(key.into()), imported[key].clone());
+            }
+        }
+    }
+
+    // the first import's memory is the file's when it gives none of its own
+    if let Yaml::Hash(top) = &mut *composed {
+        for key in REGION_ENTRIES {
+            let key_yaml = Yaml::String(key.into());
+            if !top.contains_key(&key_yaml) && !imported[key].is_badvalue() {
+                top.insert(key_yaml, imported[key].clone());
+            }
+        }
+    }
+    let mut aliases = allowed_aliases(composed);
+    aliases.extend(allowed_aliases(&imported).iter().map(|a| format!("{namespace}.{a}")));
+
+    let mut node = &mut *composed;
+    for key in root.split('.').filter(|k| !k.is_empty()) {
+        let Yaml::Hash(hash) = node else {
+            return Err(format!("root {root} is not a map"));
+        };
+        node = hash.entry(Yaml::String(key.into())).or_insert(Yaml::Hash(Hash::new()));
+    }
+    let Yaml::Hash(hash) = node else {
+        return Err(format!("root {root} is not a map"));
+    };
+    let key = Yaml::String(namespace.into());
+    if let Some(own) = hash.remove(&key) {
+        merge(&mut registers, own);
+    }
+    hash.insert(key, registers);
+
+    match composed {
+        Yaml::Hash(top) if !aliases.is_empty() => {
+            top.insert(Yaml::String("aliases".into()), Yaml::Array(aliases.into_iter().map(Yaml::String).collect()));
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+use std::io::Write;
+use aep_rust_common::descender::Descender;
+use aep_rust_common::yaml_descender::YamlDescender;
+use yaml_rust::yaml::{Array, Hash};
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+use crate::arrays::{expand_arrays, has_arrays};
+use crate::compose::{has_directives, load_composed};
+use crate::svd::{is_svd, svd_to_document};
+
+///
+/// The formats a register definition file may be written in
+///
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Svd,
+}
+
+///
+/// Determine the format of a register definition from its content rather than
+/// its name.  JSON is tried first for anything that opens with '{' or '[' but
+/// those may also be YAML flow collections, so YAML is the fallback.
+/// CMSIS-SVD files are recognized by their XML <device> element.
+///
+pub fn detect_format(content: &str) -> ConfigFormat {
+    if is_svd(content) {
+        return ConfigFormat::Svd;
+    }
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    if (start.starts_with('{') || start.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(start).is_ok() {
+        return ConfigFormat::Json;
+    }
+    ConfigFormat::Yaml
+}
+
+///
+/// Load a register definition document, in any supported format, into the
+/// same tree the YAML descender walks, with its arrays generated
+///
+pub fn load_document(content: &str) -> Result<Yaml, String> {
+    expand_arrays(parse_document(content)?, "")
+}
+
+/// the document as written
+fn parse_document(content: &str) -> Result<Yaml, String> {
+    match detect_format(content) {
+        ConfigFormat::Json => {
+            let value: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+                .map_err(|e| format!("invalid JSON: {e}"))?;
+            Ok(json_to_yaml(&value))
+        }
+        ConfigFormat::Svd => svd_to_document(content),
+        ConfigFormat::Yaml => {
+            let mut docs = YamlLoader::load_from_str(content).map_err(|e| format!("invalid YAML: {e}"))?;
+            if docs.is_empty() {
+                return Err("empty register definition".to_string());
+            }
+            Ok(docs.swap_remove(0))
+        }
+    }
+}
+
+///
+/// Whether YAML content can be given to the descender as it is, having nothing that
+/// is resolved when it's loaded
+///
+fn is_plain_yaml(content: &str) -> bool {
+    detect_format(content) == ConfigFormat::Yaml
+        && parse_document(content).is_ok_and(|doc| !has_directives(&doc) && !has_arrays(&doc))
+}
+
+fn json_to_yaml(value: &serde_json::Value) -> Yaml {
+    match value {
+        serde_json::Value::Null => Yaml::Null,
+        serde_json::Value::Bool(b) => Yaml::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        serde_json::Value::String(s) => Yaml::String(s.clone()),
+        serde_json::Value::Array(a) => Yaml::Array(a.iter().map(json_to_yaml).collect::<Array>()),
+        serde_json::Value::Object(o) => {
+            let mut hash = Hash::new();
+            for (k, v) in o {
+                hash.insert(Yaml::String(k.clone()), json_to_yaml(v));
+            }
+            Yaml::Hash(hash)
+        }
+    }
+}
+
+///
+/// Create a descender over a document produced by load_document
+///
+pub fn descender_from_document(doc: &Yaml) -> Result<Box<dyn Descender<dyn Write>>, String> {
+    let mut text = String::new();
+    YamlEmitter::new(&mut text).dump(doc).map_err(|e| format!("{e:?}"))?;
+    let descender = YamlDescender::new(&text, true).map_err(|e| e.to_string())?;
+    Ok(Box::new(descender))
+}
+
+///
+/// Create a descender over register definitions given as text in any supported format
+///
+pub fn descender_from_str(content: &str) -> Result<Box<dyn Descender<dyn Write>>, String> {
+    if is_plain_yaml(content) {
+        let descender = YamlDescender::new(content, true).map_err(|e| e.to_string())?;
+        return Ok(Box::new(descender));
+    }
+    descender_from_document(&load_document(content)?)
+}
+
+///
+/// Create a descender over a register definition file in any supported format, with
+/// the files it includes and imports in place and its arrays generated
+///
+pub fn load_descender(path: &str) -> Result<Box<dyn Descender<dyn Write>>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    if is_plain_yaml(&content) {
+        let descender = YamlDescender::new_from_file(path, true).map_err(|e| e.to_string())?;
+        return Ok(Box::new(descender));
+    }
+    load_composed(path).and_then(|doc| descender_from_document(&doc).map_err(|e| format!("{path}: {e}")))
+}
+
+/// A descender over a loaded register definition file
+pub type ConfigDescender = Box<dyn Descender<dyn Write>>;
+
+///
+/// Load a register definition file in any supported format as both the document,
+/// for walking the whole tree, and a descender, for resolving paths
+///
+pub fn load_config(path: &str) -> Result<(Yaml, ConfigDescender), String> {
+    let doc = load_composed(path)?;
+    let descender = load_descender(path)?;
+    Ok((doc, descender))
+}
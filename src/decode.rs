@@ -0,0 +1,102 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! A word read from the registers split into the fields the map names within it, so
+//! that GPIO.words.function2 reads as ten pin functions rather than one number.
+//!
+use crate::register_map::{RegisterDef, RegisterMap};
+use crate::register_op::RegisterOp;
+use crate::register_tool::Access;
+
+///
+/// A field of a decoded word
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedField {
+    /// its name within the word, e.g. pins[27].function
+    pub name: String,
+    pub hi: u32,
+    pub lo: u32,
+    pub value: u64,
+    pub label: Option<String>,
+}
+
+///
+/// The fields of the word holding the register at path, highest bits first, with
+/// their values in word.  A field's path decodes the word it is in.
+///
+pub fn decode(map: &RegisterMap, path: &str, word: u64) -> Result<(String, Vec<DecodedField>), String> {
+    let def = map.get(path).ok_or(format!("{path} not found in the register map"))?;
+    let mut fields: Vec<&RegisterDef> = map.fields_of(&def.word).collect();
+    fields.sort_by_key(|f| std::cmp::Reverse(f.op.shift));
+    let decoded = fields.iter().map(|f| {
+        let value = f.op.field_of(word);
+        let (hi, lo) = f.op.bits();
+        DecodedField {
+            name: f.field_name().to_string(),
+            hi,
+            lo,
+            value,
+            label: f.op.label(value).map(|l| l.to_string()),
+        }
+    }).collect();
+    Ok((def.word.clone(), decoded))
+}
+
+///
+/// A table of the fields of the word read or written by access of reg, headed by the
+/// word, its value and where it is
+///
+/// ```text
+/// GPIO.words.function2 = 0x00200000 at 0x7E200008, offset 0x8
+///   field              bits   value  label
+///   pins[27].function  23:21  0x1    output
+/// ```
+///
+pub fn decode_table(word_path: &str, reg: &RegisterOp, access: &Access, fields: &[DecodedField]) -> String {
+    let digits = reg.width.bits().div_ceil(4) as usize;
+    let mut text = format!("{word_path} = 0x{:0digits$X} at 0x{:08X}, offset 0x{:X}\n",
+                           access.word, access.address, reg.offset);
+    if fields.is_empty() {
+        text.push_str("  no named fields\n");
+        return text;
+    }
+    let rows: Vec<[String; 4]> = fields.iter().map(|f| [
+        f.name.clone(),
+        format!("{}:{}", f.hi, f.lo),
+        format!("0x{:X}", f.value),
+        f.label.clone().unwrap_or_default(),
+    ]).collect();
+    let header = ["field", "bits", "value", "label"].map(|h| h.to_string());
+    let widths: Vec<usize> = (0..4)
+        .map(|c| rows.iter().chain(std::iter::once(&header)).map(|r| r[c].len()).max().unwrap_or(0))
+        .collect();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line = format!("  {:<w0$}  {:<w1$}  {:<w2$}  {}",
+                           row[0], row[1], row[2], row[3], w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
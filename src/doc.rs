@@ -0,0 +1,263 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Register reference documentation, as Markdown or a self-contained HTML page,
+//! rendered from the same register map the tool uses.
+//!
+use crate::register_map::{identifier, RegisterDef, RegisterMap};
+
+///
+/// A stretch of a word's bits in its diagram, a field or an unused gap
+///
+struct Cell<'a> {
+    hi: u32,
+    lo: u32,
+    field: Option<&'a RegisterDef>,
+}
+
+/// the fields of a word, or the word itself when it has none
+fn word_fields<'a>(map: &'a RegisterMap, word: &'a RegisterDef) -> Vec<&'a RegisterDef> {
+    let fields: Vec<&RegisterDef> = map.fields_of(word.path()).collect();
+    if fields.is_empty() { vec![word] } else { fields }
+}
+
+///
+/// The diagram of a word from its top bit down, fields that overlap one already
+/// placed are left out of it
+///
+fn diagram<'a>(word: &RegisterDef, fields: &[&'a RegisterDef]) -> Vec<Cell<'a>> {
+    let mut sorted: Vec<&RegisterDef> = fields.to_vec();
+    sorted.sort_by_key(|f| std::cmp::Reverse(f.op.bits().0));
+
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut next: i64 = word.op.width.bits() as i64 - 1;
+    for field in sorted {
+        let (hi, lo) = field.op.bits();
+        if (hi as i64) > next {
+            continue;
+        }
+        if (hi as i64) < next {
+            cells.push(Cell { hi: next as u32, lo: hi + 1, field: None });
+        }
+        cells.push(Cell { hi, lo, field: Some(field) });
+        next = lo as i64 - 1;
+    }
+    if next >= 0 {
+        cells.push(Cell { hi: next as u32, lo: 0, field: None });
+    }
+    cells
+}
+
+fn hex(value: u64, width_bits: u32) -> String {
+    format!("0x{:01$X}", value, (width_bits as usize).div_ceil(4))
+}
+
+fn reset(def: &RegisterDef, width_bits: u32) -> String {
+    match def.reset {
+        Some(r) => hex(r, width_bits),
+        None => String::new(),
+    }
+}
+
+fn bit_range(hi: u32, lo: u32) -> String {
+    if hi == lo { format!("{hi}") } else { format!("{hi}:{lo}") }
+}
+
+fn values(def: &RegisterDef) -> Vec<String> {
+    let width = def.op.field_width() as usize;
+    def.op.values.iter().map(|(label, v)| format!("{label}=0b{v:0width$b}")).collect()
+}
+
+/// the words of each block, in file order
+fn blocks(map: &RegisterMap) -> Vec<(&str, Vec<&RegisterDef>)> {
+    map.blocks().into_iter()
+        .map(|b| (b, map.words().filter(|r| map.block(r.path()) == b).collect::<Vec<&RegisterDef>>()))
+        .filter(|(_, words)| !words.is_empty())
+        .collect()
+}
+
+fn md_text(text: &str) -> String {
+    text.replace('<', "&lt;")
+}
+
+fn md_cell(text: &str) -> String {
+    md_text(text).replace('|', "\\|").replace('\n', " ")
+}
+
+///
+/// The register map as Markdown, a summary table for each block and a
+/// section for each of its registers with a diagram and table of its fields
+///
+pub fn markdown(map: &RegisterMap, name: &str) -> String {
+    let mut out = format!("# {name}\n\n");
+    if !map.description.is_empty() {
+        out.push_str(&format!("{}\n\n", md_text(&map.description)));
+    }
+    out.push_str("| Device | Base | Length |\n|--------|------|--------|\n");
+    out.push_str(&format!("| {} | {} | {} |\n", md_cell(&map.device), hex(map.base, 32), hex(map.length, 32)));
+
+    for (block, words) in blocks(map) {
+        let base = map.region(block).base;
+        out.push_str(&format!("\n## {block}\n\n"));
+        if let Some((_, region)) = map.regions.iter().find(|(b, _)| b == block) {
+            out.push_str(&format!("Device {}, base {}, length {}\n\n", md_text(&region.device),
+                                  hex(region.base, 32), hex(region.length, 32)));
+        }
+        out.push_str("| Register | Offset | Address | Width | Access | Reset | Description |\n");
+        out.push_str("|----------|--------|---------|-------|--------|-------|-------------|\n");
+        for word in &words {
+            let width = word.op.width.bits();
+            out.push_str(&format!("| {} | {} | {} | {} | {} | {} | {} |\n", md_cell(word.path()),
+                                  hex(word.op.offset, 16), hex(base + word.op.offset, 32), width,
+                                  word.op.access_type().as_str(), reset(word, width),
+                                  md_cell(word.description.as_deref().unwrap_or(""))));
+        }
+
+        for word in words {
+            let width = word.op.width.bits();
+            out.push_str(&format!("\n### {}\n\n", word.path()));
+            out.push_str(&format!("Offset {}, address {}, {} bits, {}", hex(word.op.offset, 16),
+                                  hex(base + word.op.offset, 32), width, word.op.access_type().as_str()));
+            if let Some(r) = word.reset {
+                out.push_str(&format!(", reset {}", hex(r, width)));
+            }
+            out.push_str("\n\n");
+            if let Some(d) = &word.description {
+                out.push_str(&format!("{}\n\n", md_text(d)));
+            }
+
+            let fields = word_fields(map, word);
+            let cells = diagram(word, &fields);
+            let ranges: Vec<String> = cells.iter().map(|c| bit_range(c.hi, c.lo)).collect();
+            let names: Vec<String> = cells.iter()
+                .map(|c| c.field.map(|f| md_cell(f.field_name())).unwrap_or_else(|| "-".to_string()))
+                .collect();
+            out.push_str(&format!("| {} |\n", ranges.join(" | ")));
+            out.push_str(&format!("|{}\n", "---|".repeat(cells.len())));
+            out.push_str(&format!("| {} |\n\n", names.join(" | ")));
+
+            out.push_str("| Field | Bits | Access | Reset | Values | Description |\n");
+            out.push_str("|-------|------|--------|-------|--------|-------------|\n");
+            for field in fields {
+                let (hi, lo) = field.op.bits();
+                let field_reset = if field.is_field() { reset(field, field.op.field_width()) } else { String::new() };
+                out.push_str(&format!("| {} | {} | {} | {} | {} | {} |\n", md_cell(field.path()), bit_range(hi, lo),
+                                      field.op.access_type().as_str(), field_reset, md_cell(&values(field).join(", ")),
+                                      md_cell(field.description.as_deref().unwrap_or(""))));
+            }
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #999; padding: 0.2em 0.5em; text-align: left; }
+th { background: #eee; }
+table.bits td, table.bits th { text-align: center; font-size: 0.8em; min-width: 1.5em; }
+table.bits td.field { background: #dde8f5; }
+table.bits td.gap { background: #f4f4f4; color: #999; }
+code { font-size: 0.9em; }
+";
+
+///
+/// The register map as a single HTML page with its style inline, laid out as the
+/// Markdown with one column per bit in the diagrams
+///
+pub fn html(map: &RegisterMap, name: &str) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n", escape(name)));
+    out.push_str(&format!("<h1>{}</h1>\n", escape(name)));
+    if !map.description.is_empty() {
+        out.push_str(&format!("<p>{}</p>\n", escape(&map.description)));
+    }
+    out.push_str("<table>\n<tr><th>Device</th><th>Base</th><th>Length</th></tr>\n");
+    out.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n</table>\n",
+                          escape(&map.device), hex(map.base, 32), hex(map.length, 32)));
+
+    for (block, words) in blocks(map) {
+        let base = map.region(block).base;
+        out.push_str(&format!("<h2 id=\"{}\">{}</h2>\n", identifier(block), escape(block)));
+        if let Some((_, region)) = map.regions.iter().find(|(b, _)| b == block) {
+            out.push_str(&format!("<p>Device {}, base {}, length {}</p>\n", escape(&region.device),
+                                  hex(region.base, 32), hex(region.length, 32)));
+        }
+        out.push_str("<table>\n");
+        out.push_str("<tr><th>Register</th><th>Offset</th><th>Address</th><th>Width</th><th>Access</th><th>Reset</th><th>Description</th></tr>\n");
+        for word in &words {
+            let width = word.op.width.bits();
+            out.push_str(&format!("<tr><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                                  identifier(word.path()), escape(word.path()), hex(word.op.offset, 16),
+                                  hex(base + word.op.offset, 32), width, word.op.access_type().as_str(),
+                                  reset(word, width), escape(word.description.as_deref().unwrap_or(""))));
+        }
+        out.push_str("</table>\n");
+
+        for word in words {
+            let width = word.op.width.bits();
+            out.push_str(&format!("<h3 id=\"{}\">{}</h3>\n<p>Offset {}, address {}, {} bits, {}", identifier(word.path()),
+                                  escape(word.path()), hex(word.op.offset, 16), hex(base + word.op.offset, 32),
+                                  width, word.op.access_type().as_str()));
+            if let Some(r) = word.reset {
+                out.push_str(&format!(", reset {}", hex(r, width)));
+            }
+            out.push_str("</p>\n");
+            if let Some(d) = &word.description {
+                out.push_str(&format!("<p>{}</p>\n", escape(d)));
+            }
+
+            let fields = word_fields(map, word);
+            out.push_str("<table class=\"bits\">\n<tr>");
+            for bit in (0..width).rev() {
+                out.push_str(&format!("<th>{bit}</th>"));
+            }
+            out.push_str("</tr>\n<tr>");
+            for cell in diagram(word, &fields) {
+                let span = cell.hi - cell.lo + 1;
+                match cell.field {
+                    Some(f) => out.push_str(&format!("<td class=\"field\" colspan=\"{span}\">{}</td>", escape(f.field_name()))),
+                    None => out.push_str(&format!("<td class=\"gap\" colspan=\"{span}\"></td>")),
+                }
+            }
+            out.push_str("</tr>\n</table>\n");
+
+            out.push_str("<table>\n<tr><th>Field</th><th>Bits</th><th>Access</th><th>Reset</th><th>Values</th><th>Description</th></tr>\n");
+            for field in fields {
+                let (hi, lo) = field.op.bits();
+                let field_reset = if field.is_field() { reset(field, field.op.field_width()) } else { String::new() };
+                out.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                                      escape(field.path()), bit_range(hi, lo), field.op.access_type().as_str(),
+                                      field_reset, values(field).iter().map(|v| escape(v)).collect::<Vec<String>>().join("<br>"),
+                                      escape(field.description.as_deref().unwrap_or(""))));
+            }
+            out.push_str("</table>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
@@ -24,4 +24,18 @@
 mod unittests;
 pub mod unsafes;
 pub mod register_tool;
-pub mod register_op;
\ No newline at end of file
+pub mod register_op;
+pub mod config;
+pub mod compose;
+pub mod arrays;
+pub mod svd;
+pub mod register_map;
+pub mod codegen;
+pub mod pac;
+pub mod doc;
+pub mod validate;
+pub mod address_space;
+pub mod value;
+pub mod output;
+pub mod decode;
+pub mod snapshot;
\ No newline at end of file
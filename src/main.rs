@@ -21,27 +21,38 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 // 
-use std::fs::File;
 use std::io::Write;
-use clap::{Arg, ArgAction, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use aep_rust_common::find_config_file::find_config_file;
 use std::process;
-use aep_rust_common::yaml_descender::YamlDescender;
+use std::path::Path;
+use register_tool::config::{load_config, load_descender};
+use register_tool::register_map::RegisterMap;
+use register_tool::svd::export_svd;
+use register_tool::codegen::{c_header, rust_module};
+use register_tool::doc;
+use register_tool::validate::validate;
+use register_tool::register_op::AccessOptions;
 use register_tool::register_tool::RegisterTool;
-
-mod register_op;
+use register_tool::output::OutputFormat;
+use register_tool::decode::{decode, decode_table};
+use register_tool::snapshot::{config_hash, SavedRegister, Snapshot};
 
 fn main() {
     let options = Command::new("register_tool")
         .version("0.1.0")
         .author("Register Tool Developer")
         .about("Memory register read/write utility")
+        .subcommand_negates_reqs(true)
+        .subcommand_precedence_over_arg(true)
         .arg(Arg::new("file")
             .short('f')
+            .global(true)
             .help("File of reg definitions, overriding defaults"))
         .arg(Arg::new("verbose")
             .short('v')
             .action(ArgAction::SetTrue)
+            .help("Report the physical memory mapped")
             .required(false))
         .arg(Arg::new("test")
             .short('t')
@@ -55,57 +66,180 @@ fn main() {
             .long("dump")
             .action(ArgAction::SetTrue)
             .help("Dump the properties of this register, do not set or read"))
+        .arg(Arg::new("force")
+            .long("force")
+            .action(ArgAction::SetTrue)
+            .help("Read registers that are cleared by reading"))
+        .arg(Arg::new("truncate")
+            .long("truncate")
+            .action(ArgAction::SetTrue)
+            .help("Mask values to the width of their field instead of failing"))
+        .arg(Arg::new("format")
+            .long("format")
+            .value_parser(OutputFormat::NAMES)
+            .default_value("hex")
+            .conflicts_with_all(["decode", "dump-all"])
+            .help("How each register read or set is printed"))
+        .arg(Arg::new("decode")
+            .long("decode")
+            .action(ArgAction::SetTrue)
+            .help("Print every named field of the words read or set"))
+        .arg(Arg::new("dump-all")
+            .long("dump-all")
+            .value_name("PREFIX")
+            .num_args(0..=1)
+            .default_missing_value("")
+            .help("Read and decode every register, or those under PREFIX, skipping ones reading would disturb"))
         .arg(Arg::new("registers")
             .help("Register names to access")
-            .required(true)
+            .required_unless_present("dump-all")
+            .conflicts_with("dump-all")
             .trailing_var_arg(true).num_args(1..))
+        .subcommand(Command::new("export")
+            .about("Write the register definitions in another format")
+            .arg(Arg::new("format")
+                .long("format")
+                .value_parser(["svd"])
+                .default_value("svd")
+                .help("Output format")))
+        .subcommand(Command::new("snapshot")
+            .about("Save the registers to a file, or restore them from one")
+            .subcommand_required(true)
+            .subcommand(Command::new("save")
+                .about("Save every register that can be read, or those under PREFIX")
+                .arg(Arg::new("snapshot")
+                    .required(true)
+                    .help("File to save to"))
+                .arg(Arg::new("prefix")
+                    .help("Save only the registers under this path")))
+            .subcommand(Command::new("restore")
+                .about("Write back the registers saved in a file made from the same register map")
+                .arg(Arg::new("snapshot")
+                    .required(true)
+                    .help("File saved by snapshot save"))))
+        .subcommand(Command::new("generate")
+            .about("Generate source code from the register definitions")
+            .arg(Arg::new("target")
+                .value_parser(["c-header", "rust"])
+                .required(true)
+                .help("Kind of source to generate"))
+            .arg(Arg::new("structs")
+                .long("structs")
+                .action(ArgAction::SetTrue)
+                .help("Include packed struct overlays of each block")))
+        .subcommand(Command::new("doc")
+            .about("Render reference documentation of the register definitions")
+            .arg(Arg::new("format")
+                .long("format")
+                .value_parser(["markdown", "html"])
+                .default_value("markdown")
+                .help("Output format")))
+        .subcommand(Command::new("validate")
+            .about("Check register definition files, reporting every error and warning")
+            .arg(Arg::new("files")
+                .help("Files to check, by default the register file")
+                .num_args(0..))
+            .arg(Arg::new("warnings-as-errors")
+                .short('W')
+                .long("warnings-as-errors")
+                .action(ArgAction::SetTrue)
+                .help("Fail if there are warnings"))
+            .arg(Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(ArgAction::SetTrue)
+                .help("Only set the exit status"))
+            .arg(Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::SetTrue)
+                .help("List each register as it is checked and notes such as gaps"))
+            .arg(Arg::new("root")
+                .long("root")
+                .help("Check the registers under this key instead of completion-metadata.root")))
         .get_matches();
 
-    let registers: Vec<&str> = options
-        .get_many::<String>("registers")
-        .expect("Required argument missing")
-        .map(|s| s.as_str())
-        .collect();
-
-    let config_file = match options.get_one::<String>("file") {
-        Some(s) => s,
-        None => &{
-            match find_config_file("register_tool", "REGISTER_TOOL_CONFIG") {
-                Ok(s) => s,
-                Err(e) => {
-                    eprintln!("{}", e);
-                    process::exit(2);
+    if let Some(("validate", sub)) = options.subcommand() {
+        let files: Vec<String> = match sub.get_many::<String>("files") {
+            Some(f) => f.cloned().collect(),
+            None => vec![config_path(&options)],
+        };
+        process::exit(validate_files(&files, sub));
+    }
+
+    let config_file = config_path(&options);
+
+    if let Some(("export", sub)) = options.subcommand() {
+        let format = sub.get_one::<String>("format").map(|s| s.as_str()).unwrap_or("svd");
+        process::exit(export(&config_file, format));
+    }
+    if let Some(("doc", sub)) = options.subcommand() {
+        let format = sub.get_one::<String>("format").map(|s| s.as_str()).unwrap_or("markdown");
+        process::exit(document(&config_file, format));
+    }
+    if let Some(("snapshot", sub)) = options.subcommand() {
+        process::exit(match sub.subcommand() {
+            Some(("save", save)) => snapshot_save(&config_file,
+                                                   save.get_one::<String>("snapshot").unwrap(),
+                                                   save.get_one::<String>("prefix").map(|s| s.as_str()).unwrap_or(""),
+                                                   &options),
+            Some(("restore", restore)) => snapshot_restore(&config_file, restore.get_one::<String>("snapshot").unwrap(), &options),
+            _ => 2,
+        });
+    }
+    if let Some(("generate", sub)) = options.subcommand() {
+        let target = sub.get_one::<String>("target").map(|s| s.as_str()).unwrap_or("c-header");
+        process::exit(generate(&config_file, target, sub.get_flag("structs")));
+    }
+
+    let force = *options.get_one::<bool>("force").unwrap_or(&false);
+    let dump_all = options.get_one::<String>("dump-all");
+    let map = match options.get_flag("decode") || dump_all.is_some() {
+        false => None,
+        true => match load_map(&config_file) {
+            Ok(m) => Some(m),
+            Err(status) => process::exit(status),
+        },
+    } ;
+
+    /*
+     * --dump-all reads every word under its prefix that can be read without upsetting
+     * it, and lists the rest
+     */
+    let mut not_read: Vec<(&str, &str)> = Vec::new();
+    let registers: Vec<&str> = match (dump_all, &map) {
+        (Some(prefix), Some(map)) => {
+            let words = map.words_under(prefix);
+            if words.is_empty() {
+                eprintln!("No registers found under {}", prefix);
+                process::exit(1);
+            }
+            let mut readable: Vec<&str> = Vec::new();
+            for word in words {
+                match word.op.read_hazard(force) {
+                    Some(reason) => not_read.push((word.path(), reason)),
+                    None => readable.push(word.path()),
                 }
             }
+            readable
         }
-    } ;
-    
-    let descender = if config_file.ends_with(".yaml") {
-        let yd  = match YamlDescender::new_from_file(config_file, true) {
-            Ok(yd) => yd,
-            Err(e) => {
-                eprintln!("{}", e);
-                process::exit(2);
-            }
-        } ;
-        Box::new(yd)
-    } else if config_file.ends_with(".json") {
-        todo!("JSON config file support")
-    } else {
-        eprintln!("uknonwn file type: {}", config_file);
-        process::exit(3);
+        _ => options
+            .get_many::<String>("registers")
+            .expect("Required argument missing")
+            .map(|s| s.as_str())
+            .collect(),
     } ;
 
-    let mut register_tool = match RegisterTool::new(descender) {
-       Ok(rt) => rt,
-        Err(errs) => {
-            for e in errs {
-                eprintln!("{}", e);
-            }
-            process::exit(4);
+    let descender = match load_descender(&config_file) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
         }
     } ;
 
+    let mut register_tool = RegisterTool::new(descender) ;
+
 
     if *options.get_one::<bool>("dump").unwrap_or(&false) {
         match register_tool.dump_registers(&registers) {
@@ -117,6 +251,11 @@ fn main() {
         }
     }
 
+    register_tool.set_options(AccessOptions {
+        force,
+        truncate: *options.get_one::<bool>("truncate").unwrap_or(&false),
+    });
+
     /*
      * gather up all the registers to read or set.
      * If there are erroneous registers report them all and exit
@@ -131,31 +270,387 @@ fn main() {
         }
     } ;
 
+    if let Err(status) = map_memory(&mut register_tool, &options) {
+        process::exit(status);
+    }
+    
+    let format = OutputFormat::parse(options.get_one::<String>("format").unwrap()).unwrap();
+    if let (None, Some(header)) = (&map, format.header()) {
+        println!("{}", header);
+    }
+    let results =  register_tool.access_registers(|reg, access| {
+        match &map {
+            Some(map) => {
+                let (word, fields) = decode(map, &reg.path, access.word)?;
+                print!("{}", decode_table(&word, reg, access, &fields));
+            }
+            None => println!("{}", format.format(reg, access)),
+        }
+        Ok(access.value)
+    }) ;
+    let results = match results {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    } ;
+
+    if !not_read.is_empty() {
+        eprintln!("Not read:");
+        for (path, reason) in &not_read {
+            eprintln!("  {}, {}", path, reason);
+        }
+    }
+
+    for r in results {
+        match r {
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    process::exit(0);
+}
+
+///
+/// Map the memory of the gathered registers, or stand a buffer in for it in test mode
+///
+fn map_memory(register_tool: &mut RegisterTool, options: &ArgMatches) -> Result<(), i32> {
     if *options.get_one::<bool>("test").unwrap_or(&false) {
         register_tool.set_test_area() ;
     } else {
         match register_tool.set_base_address() {
-            Ok(_) => {}
+            Ok(_) => {
+                if options.get_flag("verbose") {
+                    for (device, start, end) in register_tool.physical_ranges() {
+                        eprintln!("Mapped physical range 0x{:X}-0x{:X} of {}", start, end - 1, device);
+                    }
+                }
+            }
             Err(e) => {
                 eprintln!("{}", e);
-                process::exit(1);
+                return Err(1);
             }       
         }
     }
-    
-    let results =  register_tool.apply_registers(|v| {
-        println!("{}", v);
-        Ok(v)
-    }).unwrap() ;
+    Ok(())
+}
+
+///
+/// A RegisterTool with the registers of specs gathered and their memory mapped
+///
+fn tool_for(config_file: &str, specs: &[String], options: &ArgMatches) -> Result<RegisterTool, i32> {
+    let descender = load_descender(config_file).map_err(|e| {
+        eprintln!("{}", e);
+        2
+    })?;
+    let mut register_tool = RegisterTool::new(descender);
+    register_tool.set_options(AccessOptions {
+        force: options.get_flag("force"),
+        truncate: false,
+    });
+    let specs: Vec<&str> = specs.iter().map(|s| s.as_str()).collect();
+    register_tool.gather_regs(&specs).map_err(|errs| {
+        errs.iter().for_each(|e| eprintln!("{}", e));
+        1
+    })?;
+    map_memory(&mut register_tool, options)?;
+    Ok(register_tool)
+}
 
+///
+/// Save the words under prefix that can be read without disturbing them to file,
+/// returning the exit code
+///
+fn snapshot_save(config_file: &str, file: &str, prefix: &str, options: &ArgMatches) -> i32 {
+    let (doc, mut descender) = match load_config(config_file) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let map = match RegisterMap::new(&doc, &mut *descender, "") {
+        Ok(m) => m,
+        Err(errs) => {
+            errs.iter().for_each(|e| eprintln!("{}", e));
+            return 4;
+        }
+    };
+    let hash = match config_hash(&doc) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 4;
+        }
+    };
+    let words = map.words_under(prefix);
+    if words.is_empty() {
+        eprintln!("No registers found under {}", prefix);
+        return 1;
+    }
+    let mut paths: Vec<String> = Vec::new();
+    for word in words {
+        match word.op.read_hazard(options.get_flag("force")) {
+            Some(reason) => eprintln!("Not saved: {}, {}", word.path(), reason),
+            None => paths.push(word.path().to_string()),
+        }
+    }
+    let register_tool = match tool_for(config_file, &paths, options) {
+        Ok(t) => t,
+        Err(status) => return status,
+    };
+
+    let saved = std::cell::RefCell::new(Vec::new());
+    let results = register_tool.access_registers(|reg, access| {
+        saved.borrow_mut().push(SavedRegister { path: reg.path.clone(), address: access.address, word: access.word });
+        Ok(access.value)
+    });
+    let results = match results {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
     for r in results {
-        match r {
-            Ok(_) => {}
+        if let Err(e) = r {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+    let snapshot = Snapshot { hash, registers: saved.into_inner() };
+    if let Err(e) = std::fs::write(file, snapshot.to_yaml()) {
+        eprintln!("Failed to write {}: {}", file, e);
+        return 1;
+    }
+    0
+}
+
+///
+/// Write the words saved in file back, if it was saved from the same register map, and
+/// return the exit code
+///
+fn snapshot_restore(config_file: &str, file: &str, options: &ArgMatches) -> i32 {
+    let snapshot = match std::fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|c| Snapshot::parse(&c)) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return 2;
+        }
+    };
+    let (doc, mut descender) = match load_config(config_file) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let map = match RegisterMap::new(&doc, &mut *descender, "") {
+        Ok(m) => m,
+        Err(errs) => {
+            errs.iter().for_each(|e| eprintln!("{}", e));
+            return 4;
+        }
+    };
+    let checked = config_hash(&doc).map_err(|e| vec![e]).and_then(|hash| snapshot.check(&map, &hash));
+    if let Err(errs) = checked {
+        errs.iter().for_each(|e| eprintln!("{}", e));
+        eprintln!("Not restoring {}", file);
+        return 1;
+    }
+
+    let (settings, skipped) = snapshot.restore_plan(&map);
+    let register_tool = match tool_for(config_file, &settings, options) {
+        Ok(t) => t,
+        Err(status) => return status,
+    };
+    let results = register_tool.access_registers(|reg, access| {
+        println!("{}", OutputFormat::Hex.format(reg, access));
+        Ok(access.value)
+    });
+    let results = match results {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    for (path, reason) in &skipped {
+        eprintln!("Not restored: {}, {}", path, reason);
+    }
+    for r in results {
+        if let Err(e) = r {
+            eprintln!("{}", e);
+            return 1;
+        }
+    }
+    0
+}
+
+fn config_path(options: &ArgMatches) -> String {
+    match options.get_one::<String>("file") {
+        Some(s) => s.clone(),
+        None => match find_config_file("register_tool", "REGISTER_TOOL_CONFIG") {
+            Ok(s) => s,
             Err(e) => {
-                process::exit(1);
+                eprintln!("{}", e);
+                process::exit(2);
             }
         }
     }
+}
 
-    process::exit(0);
+///
+/// Load every register in the config file, reporting errors and returning
+/// the exit code on failure
+///
+fn load_map(config_file: &str) -> Result<RegisterMap, i32> {
+    let (doc, mut descender) = match load_config(config_file) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(2);
+        }
+    };
+    match RegisterMap::new(&doc, &mut *descender, "") {
+        Ok(m) => Ok(m),
+        Err(errs) => {
+            for e in errs {
+                eprintln!("{}", e);
+            }
+            Err(4)
+        }
+    }
+}
+
+fn config_name(config_file: &str) -> &str {
+    Path::new(config_file).file_stem().and_then(|s| s.to_str()).unwrap_or("device")
+}
+
+///
+/// Write every register in the config file to stdout in the given format,
+/// returning the exit code
+///
+fn export(config_file: &str, format: &str) -> i32 {
+    let map = match load_map(config_file) {
+        Ok(m) => m,
+        Err(status) => return status,
+    };
+    match format {
+        "svd" => {
+            let _ = std::io::stdout().write_all(export_svd(&map, config_name(config_file)).as_bytes());
+        }
+        _ => {
+            eprintln!("Unknown export format {}", format);
+            return 2;
+        }
+    }
+    0
+}
+
+///
+/// Write source code for every register in the config file to stdout,
+/// returning the exit code
+///
+fn generate(config_file: &str, target: &str, structs: bool) -> i32 {
+    let map = match load_map(config_file) {
+        Ok(m) => m,
+        Err(status) => return status,
+    };
+    let source = match target {
+        "c-header" => c_header(&map, config_name(config_file), structs),
+        "rust" => rust_module(&map, config_name(config_file), "register_tool"),
+        _ => Err(vec![format!("Unknown generate target {}", target)]),
+    };
+    match source {
+        Ok(source) => {
+            let _ = std::io::stdout().write_all(source.as_bytes());
+            0
+        }
+        Err(errs) => {
+            for e in errs {
+                eprintln!("{}", e);
+            }
+            4
+        }
+    }
+}
+
+///
+/// Write documentation of every register in the config file to stdout,
+/// returning the exit code
+///
+fn document(config_file: &str, format: &str) -> i32 {
+    let map = match load_map(config_file) {
+        Ok(m) => m,
+        Err(status) => return status,
+    };
+    let text = match format {
+        "markdown" => doc::markdown(&map, config_name(config_file)),
+        "html" => doc::html(&map, config_name(config_file)),
+        _ => {
+            eprintln!("Unknown doc format {}", format);
+            return 2;
+        }
+    };
+    let _ = std::io::stdout().write_all(text.as_bytes());
+    0
+}
+
+///
+/// Check each file, printing the warnings and errors found in all of them.  The exit
+/// code is 1 if there are errors, or warnings and -W, 2 if a file cannot be read
+///
+fn validate_files(files: &[String], options: &ArgMatches) -> i32 {
+    let quiet = options.get_flag("quiet");
+    let verbose = options.get_flag("verbose");
+    let mut notes: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for file in files {
+        let (doc, mut descender) = match load_config(file) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
+        };
+        let root = options.get_one::<String>("root").map(|s| s.as_str());
+        let report = validate(&doc, &mut *descender, root, verbose);
+        let prefix = if files.len() > 1 { format!("{file}: ") } else { String::new() };
+        notes.extend(report.notes.iter().map(|n| format!("{prefix}{n}")));
+        warnings.extend(report.warnings.iter().map(|w| format!("{prefix}{w}")));
+        errors.extend(report.errors.iter().map(|e| format!("{prefix}{e}")));
+    }
+
+    if !quiet {
+        if verbose && !notes.is_empty() {
+            println!("notes: {}", notes.len());
+            for n in &notes {
+                println!("  {}", n);
+            }
+        }
+        if !warnings.is_empty() {
+            println!("warnings: {}", warnings.len());
+            for w in &warnings {
+                println!("  {}", w);
+            }
+        }
+        if !errors.is_empty() {
+            println!("errors: {}", errors.len());
+            for e in &errors {
+                println!("  {}", e);
+            }
+        }
+    }
+    if !errors.is_empty() || (options.get_flag("warnings-as-errors") && !warnings.is_empty()) {
+        return 1;
+    }
+    0
 }
@@ -0,0 +1,169 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! How the registers read or set on the command line are printed.  Every line names
+//! its register, so that the results of several registers can be told apart.
+//!
+use serde_json::json;
+use crate::register_op::RegisterOp;
+use crate::register_tool::Access;
+
+///
+/// A format for the results of register accesses, from --format
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// path = 0x1F, the default
+    Hex,
+    Decimal,
+    Binary,
+    /// the field as two's complement of its width
+    Signed,
+    /// the hex line followed by the register's bits with the field marked
+    Diagram,
+    /// an object per line
+    Json,
+    /// a header line, then a line per register
+    Csv,
+}
+
+impl OutputFormat {
+    /// the names accepted by --format
+    pub const NAMES: [&'static str; 7] = ["hex", "decimal", "binary", "signed", "diagram", "json", "csv"];
+
+    pub fn parse(name: &str) -> Result<OutputFormat, String> {
+        match name {
+            "hex" => Ok(OutputFormat::Hex),
+            "decimal" => Ok(OutputFormat::Decimal),
+            "binary" => Ok(OutputFormat::Binary),
+            "signed" => Ok(OutputFormat::Signed),
+            "diagram" => Ok(OutputFormat::Diagram),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("Unknown output format '{name}', must be one of {}", OutputFormat::NAMES.join(", "))),
+        }
+    }
+
+    /// the line to print before any results
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Csv => Some("path,address,word,value,bits,access,label"),
+            _ => None,
+        }
+    }
+
+    ///
+    /// The lines printed for access of reg, without a trailing newline
+    ///
+    pub fn format(&self, reg: &RegisterOp, access: &Access) -> String {
+        let field_width = reg.field_width() as usize;
+        let value = access.value;
+        let label = reg.label(value);
+        let (hi, lo) = reg.bits();
+        match self {
+            OutputFormat::Hex => named(reg, &hex(value, field_width), label),
+            OutputFormat::Decimal => named(reg, &value.to_string(), label),
+            OutputFormat::Binary => named(reg, &format!("0b{value:0field_width$b}"), label),
+            OutputFormat::Signed => named(reg, &signed(value, field_width).to_string(), label),
+            OutputFormat::Diagram => format!("{}\n{}", named(reg, &hex(value, field_width), label), diagram(reg, access.word)),
+            OutputFormat::Json => json!({
+                "path": reg.path,
+                "address": access.address,
+                "word": access.word,
+                "value": value,
+                "bits": format!("{hi}:{lo}"),
+                "access": reg.access_type().as_str(),
+                "label": label,
+            }).to_string(),
+            OutputFormat::Csv => [
+                csv_field(&reg.path),
+                format!("0x{:08X}", access.address),
+                hex(access.word, reg.width.bits() as usize),
+                value.to_string(),
+                format!("{hi}:{lo}"),
+                reg.access_type().as_str().to_string(),
+                csv_field(label.unwrap_or("")),
+            ].join(","),
+        }
+    }
+}
+
+/// path = value, with the value's label if it has one
+fn named(reg: &RegisterOp, value: &str, label: Option<&str>) -> String {
+    match label {
+        Some(l) => format!("{} = {value} ({l})", reg.path),
+        None => format!("{} = {value}", reg.path),
+    }
+}
+
+/// value in hex with a digit for every 4 of bits
+fn hex(value: u64, bits: usize) -> String {
+    format!("0x{value:0digits$X}", digits = bits.div_ceil(4).max(1))
+}
+
+/// value as a two's complement number of bits
+pub fn signed(value: u64, bits: usize) -> i64 {
+    if bits == 0 || bits >= 64 {
+        value as i64
+    } else {
+        let shift = 64 - bits;
+        ((value << shift) as i64) >> shift
+    }
+}
+
+/// text as a CSV field, quoted if it needs to be
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+///
+/// The bits of word in groups of 4, numbered above and with the bits of reg's field
+/// marked below
+///
+/// ```text
+///   31   27   23   19   15   11   7    3
+///   0000 0000 0010 0000 0000 0000 0000 0000
+///               ^^ ^
+/// ```
+///
+fn diagram(reg: &RegisterOp, word: u64) -> String {
+    let bits = reg.width.bits();
+    let mut numbers = String::from(" ");
+    let mut values = String::from(" ");
+    let mut marks = String::from(" ");
+    for bit in (0..bits).rev() {
+        if (bit + 1) % 4 == 0 {
+            numbers.push_str(&format!(" {bit:<4}"));
+            values.push(' ');
+            marks.push(' ');
+        }
+        values.push(if word >> bit & 1 == 1 { '1' } else { '0' });
+        marks.push(if reg.read_mask >> bit & 1 == 1 { '^' } else { ' ' });
+    }
+    format!("{}\n{}\n{}", numbers.trim_end(), values, marks.trim_end())
+}
@@ -0,0 +1,169 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Support for the typed register modules written by codegen::rust_module.
+//!
+//! Each block of the map becomes a type implementing Block and each register a type
+//! implementing Register, with Readable and Writable as its access allows, and its value
+//! is either an enum of its 'values' or Bits of its width, so that
+//!
+//! ```ignore
+//! let regs = Registers::<GPIO_BLOCK>::open()?;
+//! regs.write::<GPIO_PINS_27_FUNCTION>(GPIO_PINS_27_FUNCTION_VALUE::output)?;
+//! regs.write::<GPIO_PINS_27_SET>(Bits::new::<1>())?;
+//! ```
+//!
+//! fails to build when a register is misspelled, used with the memory of another block,
+//! written when read-only or given a value too wide for it.  Accesses go through
+//! RegisterOp and so use the same checks and write strategies as the tool.
+//!
+use std::marker::PhantomData;
+use crate::register_op::{AccessWidth, RegisterAccess, RegisterOp};
+use crate::unsafes::Mapping;
+
+/// The value of a register, converted to and from its bits
+pub trait FieldValue: Sized + Copy {
+    fn to_bits(self) -> u64;
+    fn from_bits(bits: u64) -> Result<Self, String>;
+}
+
+///
+/// A number that fits in a register of N bits
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bits<const N: u32>(u64);
+
+impl<const N: u32> Bits<N> {
+    pub const MAX: u64 = if N >= 64 { u64::MAX } else { (1u64 << N) - 1 };
+
+    /// a value checked as the program is built, Bits::<2>::new::<4>() is an error
+    pub const fn new<const V: u64>() -> Self {
+        const { assert!(V <= Self::MAX, "value does not fit in the register") };
+        Bits(V)
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl<const N: u32> TryFrom<u64> for Bits<N> {
+    type Error = String;
+
+    fn try_from(value: u64) -> Result<Self, String> {
+        if value > Self::MAX {
+            return Err(format!("Value {} (0x{:X}) is out of range, must be 0 to {} (0x{:X})", value, value, Self::MAX, Self::MAX));
+        }
+        Ok(Bits(value))
+    }
+}
+
+impl<const N: u32> FieldValue for Bits<N> {
+    fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    fn from_bits(bits: u64) -> Result<Self, String> {
+        Self::try_from(bits)
+    }
+}
+
+///
+/// A block of a generated module, the memory its registers' offsets are from
+///
+pub trait Block {
+    const DEVICE: &'static str;
+    const BASE: u64;
+    const LENGTH: u64;
+}
+
+///
+/// A register of a generated module, the resolved form of its definition
+///
+pub trait Register {
+    type Block: Block;
+    const PATH: &'static str;
+    const OFFSET: u64;
+    const WIDTH: AccessWidth;
+    const SHIFT: u32;
+    const MASK: u64;
+    const ACCESS: RegisterAccess;
+    const SHADOW: Option<u64>;
+    type Value: FieldValue;
+}
+
+pub trait Readable: Register {}
+
+/// an rc register, cleared by reading it, which Registers::read_clearing reads
+pub trait ReadClearable: Register {}
+
+pub trait Writable: Register {}
+
+fn register_op<R: Register>(value: Option<u64>) -> RegisterOp {
+    RegisterOp::from_parts(R::PATH, R::OFFSET, R::WIDTH, R::SHIFT, R::MASK, R::ACCESS, R::SHADOW, value)
+}
+
+///
+/// The mapped memory of block B accessed through its generated register types
+///
+pub struct Registers<B: Block> {
+    mapping: Mapping,
+    block: PhantomData<B>,
+}
+
+impl<B: Block> Registers<B> {
+    /// the block's memory mapped from its device
+    pub fn open() -> Result<Registers<B>, String> {
+        Ok(Registers::new(Mapping::device(B::DEVICE, B::BASE, B::LENGTH)?))
+    }
+
+    /// the block's registers in memory mapped some other way, a buffer for tests
+    pub fn new(mapping: Mapping) -> Registers<B> {
+        Registers { mapping, block: PhantomData }
+    }
+
+    /// the memory the registers are in, for accesses by offset
+    pub fn mapping(&self) -> &Mapping {
+        &self.mapping
+    }
+
+    fn get<R: Register<Block = B>>(&self) -> Result<R::Value, String> {
+        let bits = register_op::<R>(None).get(&self.mapping)?;
+        R::Value::from_bits(bits).map_err(|e| format!("{}: {}", R::PATH, e))
+    }
+
+    pub fn read<R: Readable + Register<Block = B>>(&self) -> Result<R::Value, String> {
+        self.get::<R>()
+    }
+
+    /// read an rc register, clearing it
+    pub fn read_clearing<R: ReadClearable + Register<Block = B>>(&self) -> Result<R::Value, String> {
+        self.get::<R>()
+    }
+
+    pub fn write<R: Writable + Register<Block = B>>(&self, value: R::Value) -> Result<(), String> {
+        register_op::<R>(Some(value.to_bits())).set(&self.mapping)?;
+        Ok(())
+    }
+}
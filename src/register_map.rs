@@ -0,0 +1,478 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+use std::io::Write;
+use aep_rust_common::descender::Descender;
+use yaml_rust::Yaml;
+use crate::register_op::RegisterOp;
+use crate::value::parse_value;
+
+/// Entries that make a node a register rather than a block of registers
+const REGISTER_ENTRIES: [&str; 5] = ["offset", "bits", "read-write", "shadow", "parent"];
+
+/// The key registers are found under, from completion-metadata.root
+pub fn document_root(doc: &Yaml) -> String {
+    doc["completion-metadata"]["root"].as_str().unwrap_or("").to_string()
+}
+
+///
+/// Find the node at a path such as "GPIO.pins[27].function" or "GPIO.pins@27.function"
+/// starting from node
+///
+pub fn find_node<'a>(node: &'a Yaml, path: &str) -> Option<&'a Yaml> {
+    let mut current = node;
+    for part in path.split('.').filter(|p| !p.is_empty()) {
+        let (key, index) = match part.find(['[', '@']) {
+            Some(i) => {
+                let index = part[i + 1..].trim_end_matches(']').parse::<usize>().ok()?;
+                (&part[..i], Some(index))
+            }
+            None => (part, None),
+        };
+        current = &current[key];
+        if let Some(i) = index {
+            current = &current[i];
+        }
+        if current.is_badvalue() {
+            return None;
+        }
+    }
+    Some(current)
+}
+
+/// Is this node a register, i.e. does it have any register entries
+pub fn is_register(node: &Yaml) -> bool {
+    match node.as_hash() {
+        Some(hash) => REGISTER_ENTRIES.iter().any(|e| hash.contains_key(&Yaml::String(e.to_string()))),
+        None => false,
+    }
+}
+
+///
+/// Paths, relative to the root, of every register at or below prefix in file order
+///
+/// Registers may contain registers, as the fields of an imported SVD register do, so
+/// the walk continues below each register found.
+///
+pub fn register_paths(doc: &Yaml, prefix: &str) -> Result<Vec<String>, String> {
+    let root = document_root(doc);
+    let root_node = find_node(doc, &root).ok_or_else(|| format!("root {root} not found"))?;
+    paths_below(root_node, prefix)
+}
+
+/// Paths of every register at or below prefix of the root node, in file order
+pub fn paths_below(root_node: &Yaml, prefix: &str) -> Result<Vec<String>, String> {
+    let start = find_node(root_node, prefix).ok_or_else(|| format!("{prefix} not found"))?;
+
+    let mut paths: Vec<String> = Vec::new();
+    walk(start, prefix, &mut paths);
+    Ok(paths)
+}
+
+fn walk(node: &Yaml, path: &str, paths: &mut Vec<String>) {
+    if is_register(node) && !path.is_empty() {
+        paths.push(path.to_string());
+    }
+    match node {
+        Yaml::Hash(hash) => {
+            for (key, value) in hash {
+                if let (Some(k), true) = (key.as_str(), matches!(value, Yaml::Hash(_) | Yaml::Array(_))) {
+                    let sep = if path.is_empty() { "" } else { "." };
+                    walk(value, &format!("{path}{sep}{k}"), paths);
+                }
+            }
+        }
+        Yaml::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                walk(value, &format!("{path}[{i}]"), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+///
+/// path followed by its parent, their parent and so on to the register holding its bits,
+/// with parent_of giving the 'parent' of a path.  A loop is an error.
+///
+pub fn parent_chain(path: &str, parent_of: impl Fn(&str) -> Option<String>) -> Result<Vec<String>, String> {
+    let mut chain: Vec<String> = vec![path.to_string()];
+    while let Some(p) = chain.last().and_then(|last| parent_of(last)) {
+        if chain.contains(&p) {
+            return Err(format!("parent loop at {p} for {path}"));
+        }
+        chain.push(p);
+    }
+    Ok(chain)
+}
+
+///
+/// The register at the end of path's chain of parents, whose block it is mapped from,
+/// or path itself when the chain loops
+///
+pub fn word_of(path: &str, parent_of: impl Fn(&str) -> Option<String>) -> String {
+    parent_chain(path, parent_of).ok().and_then(|c| c.last().cloned()).unwrap_or(path.to_string())
+}
+
+/// the 'parent' of the register at path below root_node
+pub fn parent_in(root_node: &Yaml, path: &str) -> Option<String> {
+    find_node(root_node, path).and_then(|n| n["parent"].as_str()).map(|p| p.to_string())
+}
+
+/// is path that of prefix or one below it, every path being below ""
+pub fn is_below(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.is_empty() || rest.is_empty() || rest.starts_with(['.', '[']),
+        None => false,
+    }
+}
+
+/// the nodes enclosing path, outermost first, "GPIO", "GPIO.pins" for "GPIO.pins[27]"
+pub(crate) fn enclosing(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices(['.', '[']).map(move |(i, _)| &path[..i])
+}
+
+///
+/// The block whose region the register at path is mapped from, the innermost node
+/// enclosing it for which has_region is true, by default its top level block.  Blocks of
+/// an imported file below its namespace, e.g. soc.GPIO, give their own this way.
+///
+pub fn region_block(path: &str, has_region: impl Fn(&str) -> bool) -> &str {
+    let blocks: Vec<&str> = enclosing(path).collect();
+    blocks.into_iter().rev().find(|b| has_region(b)).unwrap_or(block_of(path))
+}
+
+/// The device mapped when neither a block nor the file names one
+pub const DEFAULT_DEVICE: &str = "/dev/mem";
+
+///
+/// The device memory a block of registers is mapped from
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub device: String,
+    pub base: u64,
+    pub length: u64,
+}
+
+///
+/// The 'device', 'base' and 'length' given at one level of the file, the top for its
+/// defaults or a block for its own
+///
+#[derive(Debug, Clone, Default)]
+pub struct RegionKeys {
+    pub device: Option<String>,
+    pub base: Option<u64>,
+    pub length: Option<u64>,
+}
+
+impl RegionKeys {
+    /// the keys of the node at path, "" for the top of the file before the root is set
+    pub fn read(descender: &dyn Descender<dyn Write>, path: &str) -> RegionKeys {
+        RegionKeys {
+            device: descender.get_string_field_or_parent(path, "device").ok(),
+            base: descender.get_int_field_or_parent(path, "base").ok().map(|b| b as u64),
+            length: descender.get_int_field_or_parent(path, "length").ok().map(|l| l as u64),
+        }
+    }
+
+    ///
+    /// The keys of block with those it doesn't give taken from the blocks enclosing it,
+    /// the innermost first
+    ///
+    pub fn within(descender: &dyn Descender<dyn Write>, block: &str) -> RegionKeys {
+        let mut keys = RegionKeys::read(descender, block);
+        for outer in enclosing(block).collect::<Vec<&str>>().into_iter().rev() {
+            let more = RegionKeys::read(descender, outer);
+            keys.device = keys.device.or(more.device);
+            keys.base = keys.base.or(more.base);
+            keys.length = keys.length.or(more.length);
+        }
+        keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.device.is_none() && self.base.is_none() && self.length.is_none()
+    }
+
+    ///
+    /// The region of block, with what it doesn't give taken from the file's keys
+    ///
+    pub fn region(&self, file: &RegionKeys, block: &str) -> Result<Region, String> {
+        let device = self.device.clone().or_else(|| file.device.clone()).unwrap_or(DEFAULT_DEVICE.to_string());
+        let base = self.base.or(file.base).ok_or(format!("base not found for {block}"))?;
+        let length = self.length.or(file.length).ok_or(format!("length not found for {block}"))?;
+        Ok(Region { device, base, length })
+    }
+}
+
+///
+/// A register or field of the map with everything needed to describe it
+///
+pub struct RegisterDef {
+    pub op: RegisterOp,
+    /// the register this one takes its offset from
+    pub parent: Option<String>,
+    /// the register at the end of the parent chain, the one holding the field
+    pub word: String,
+    /// the register read in its place, its own or inherited
+    pub shadow: Option<String>,
+    /// reset value of the register, or of just the field's bits for a field
+    pub reset: Option<u64>,
+    /// its own description, not one inherited from a parent
+    pub description: Option<String>,
+}
+
+impl RegisterDef {
+    pub fn path(&self) -> &str {
+        &self.op.path
+    }
+
+    pub fn is_field(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    /// its name within its word, "UE" for USART1.CR1.UE, or else within its block
+    pub fn field_name(&self) -> &str {
+        let path = self.path();
+        match path.strip_prefix(self.word.as_str()).and_then(|p| p.strip_prefix('.')) {
+            Some(name) => name,
+            None => path.strip_prefix(block_of(path)).unwrap_or(path).trim_start_matches('.'),
+        }
+    }
+}
+
+///
+/// Every register reachable from the root, resolved as RegisterOp would
+///
+pub struct RegisterMap {
+    pub description: String,
+    pub device: String,
+    pub base: u64,
+    pub length: u64,
+    /// the 'device', 'base' and 'length' the file itself gives
+    pub file: RegionKeys,
+    pub registers: Vec<RegisterDef>,
+    /// the blocks giving any of their own 'device', 'base' or 'length', resolved
+    pub regions: Vec<(String, Region)>,
+}
+
+impl RegisterMap {
+    ///
+    /// Resolve every register at or below prefix, returning all the errors found
+    ///
+    pub fn new(doc: &Yaml, descender: &mut dyn Descender<dyn Write>, prefix: &str) -> Result<RegisterMap, Vec<String>> {
+        let mut errs: Vec<String> = Vec::new();
+
+        let description = descender.get_string_field_or_parent("", "description").unwrap_or_default();
+        let file = RegionKeys::read(&*descender, "");
+        let device = file.device.clone().unwrap_or_default();
+        let base = file.base.unwrap_or(0);
+        let length = file.length.unwrap_or(0);
+
+        let paths = register_paths(doc, prefix).map_err(|e| vec![e])?;
+        let root = document_root(doc);
+        let root_node = find_node(doc, &root).unwrap_or(doc);
+        let old_root = enter_root(descender, &root).map_err(|e| vec![e])?;
+
+        let mut registers: Vec<RegisterDef> = Vec::new();
+        for path in paths {
+            match resolve_def(root_node, &*descender, &path) {
+                Ok(def) => registers.push(def),
+                Err(e) => errs.push(e),
+            }
+        }
+
+        let mut regions: Vec<(String, Region)> = Vec::new();
+        let has_region = |b: &str| !RegionKeys::read(&*descender, b).is_empty();
+        for r in &registers {
+            for block in [region_block(r.path(), has_region), region_block(&r.word, has_region)] {
+                if regions.iter().any(|(b, _)| b == block) || !has_region(block) {
+                    continue;
+                }
+                match RegionKeys::within(&*descender, block).region(&file, block) {
+                    Ok(region) => regions.push((block.to_string(), region)),
+                    Err(e) => errs.push(e),
+                }
+            }
+        }
+
+        if let Err(e) = leave_root(descender, old_root) {
+            errs.push(e);
+        }
+
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+        Ok(RegisterMap { description, device, base, length, file, registers, regions })
+    }
+
+    pub fn get(&self, path: &str) -> Option<&RegisterDef> {
+        self.registers.iter().find(|r| r.path() == path)
+    }
+
+    /// registers that are not fields of another
+    pub fn words(&self) -> impl Iterator<Item = &RegisterDef> {
+        self.registers.iter().filter(|r| !r.is_field())
+    }
+
+    /// the fields held by the word register at path
+    pub fn fields_of<'a>(&'a self, word: &'a str) -> impl Iterator<Item = &'a RegisterDef> {
+        self.registers.iter().filter(move |r| r.is_field() && r.word == word)
+    }
+
+    ///
+    /// The words holding the registers at or below prefix, each once in file order.  The
+    /// words of fields below prefix are included though they lie elsewhere.
+    ///
+    pub fn words_under(&self, prefix: &str) -> Vec<&RegisterDef> {
+        let mut words: Vec<&RegisterDef> = Vec::new();
+        for r in self.registers.iter().filter(|r| is_below(r.path(), prefix)) {
+            match self.get(&r.word) {
+                Some(word) if !words.iter().any(|w| w.path() == word.path()) => words.push(word),
+                _ => {}
+            }
+        }
+        words
+    }
+
+    ///
+    /// The block the register at path is laid out in, the innermost one giving its own
+    /// region, by default its top level block
+    ///
+    pub fn block<'a>(&'a self, path: &'a str) -> &'a str {
+        self.regions.iter()
+            .map(|(b, _)| b.as_str())
+            .filter(|b| is_below(path, b) && path != *b)
+            .max_by_key(|b| b.len())
+            .unwrap_or(block_of(path))
+    }
+
+    /// the word aligned start and the end offset of a block's registers
+    pub fn block_span(&self, block: &str) -> (u64, u64) {
+        let words: Vec<&RegisterDef> = self.words().filter(|r| self.block(r.path()) == block).collect();
+        let start = words.iter().map(|r| r.op.offset).min().unwrap_or(0) & !3;
+        let end = words.iter().map(|r| r.op.offset + r.op.width.bytes()).max().unwrap_or(start);
+        (start, end)
+    }
+
+    /// the file's own region, when it gives a 'base' and 'length' rather than leaving them to its blocks
+    pub fn file_region(&self) -> Option<Region> {
+        self.file.region(&RegionKeys::default(), "").ok()
+    }
+
+    /// where a block's registers are, its own region or the file's
+    pub fn region(&self, block: &str) -> Region {
+        match self.regions.iter().find(|(b, _)| b == block) {
+            Some((_, region)) => region.clone(),
+            None => Region { device: self.device.clone(), base: self.base, length: self.length },
+        }
+    }
+
+    /// the blocks, e.g. GPIO, in file order, those giving their own region among them
+    pub fn blocks(&self) -> Vec<&str> {
+        let mut blocks: Vec<&str> = Vec::new();
+        for r in &self.registers {
+            let block = self.block(r.path());
+            if !blocks.contains(&block) {
+                blocks.push(block);
+            }
+        }
+        blocks
+    }
+}
+
+/// Make root, if any, the descender's root as RegisterTool does, returning the root to restore
+pub(crate) fn enter_root(descender: &mut dyn Descender<dyn Write>, root: &str) -> Result<Option<String>, String> {
+    if root.is_empty() {
+        return Ok(None);
+    }
+    descender.set_root(root).map(Some).map_err(|e| format!("Error setting root {root}: {e}"))
+}
+
+pub(crate) fn leave_root(descender: &mut dyn Descender<dyn Write>, old_root: Option<String>) -> Result<(), String> {
+    match old_root {
+        Some(r) => descender.set_root(&r).map(|_| ()).map_err(|e| format!("Error resetting old root: {e}")),
+        None => Ok(()),
+    }
+}
+
+/// the top level block of a path, "GPIO" for "GPIO.pins[27].function"
+pub fn block_of(path: &str) -> &str {
+    let end = path.find(['.', '[', '@']).unwrap_or(path.len());
+    &path[..end]
+}
+
+/// Turn a path or label into an identifier for generated code or SVD, "pins[27].function" becomes "pins_27_function"
+pub fn identifier(text: &str) -> String {
+    let mut name = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_');
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// a register's 'reset', an integer or, when too large for one, a string such as "0xFFFFFFFFFFFFFFFF"
+fn reset_of(node: &Yaml) -> Option<u64> {
+    match &node["reset"] {
+        Yaml::Integer(r) => Some(*r as u64),
+        Yaml::String(r) => parse_value(r).ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn resolve_def(root_node: &Yaml, descender: &dyn Descender<dyn Write>, path: &str) -> Result<RegisterDef, String> {
+    let op = RegisterOp::resolve(descender, path)?;
+    let node = find_node(root_node, path);
+
+    let parent = node.and_then(|n| n["parent"].as_str()).map(|p| p.to_string());
+    let chain = parent_chain(path, |p| parent_in(root_node, p))?;
+    if let Some(missing) = chain.iter().skip(1).find(|p| find_node(root_node, p).is_none()) {
+        return Err(format!("parent {missing} not found for {path}"));
+    }
+    let word = chain.last().cloned().unwrap_or(path.to_string());
+
+    let own_reset = node.and_then(reset_of);
+    let reset = match (own_reset, parent.is_some()) {
+        (Some(r), _) => Some(r),
+        (None, false) => None,
+        (None, true) => chain.iter().skip(1).find_map(|p| find_node(root_node, p).and_then(reset_of))
+            .map(|r| (r & op.read_mask) >> op.shift),
+    };
+    let description = node.and_then(|n| n["description"].as_str()).map(|d| d.to_string());
+    let shadow = match op.shadow_offset() {
+        Some(_) => descender.get_string_field_or_parent(path, "shadow").ok(),
+        None => None,
+    };
+
+    Ok(RegisterDef { op, parent, word, shadow, reset, description })
+}
@@ -23,77 +23,234 @@
 // 
 use std::io::Write;
 use aep_rust_common::descender::Descender;
+use crate::value::parse_value;
+pub use crate::unsafes::AccessWidth;
+use crate::unsafes::Mapping;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum RegisterAccess {
     ReadOnly,
     ReadWrite,
     WriteOnly,
-    Write1Clear,
+    Write1Clear, // writing 1 clears a bit, 0 leaves it
+    Write1Set,   // writing 1 sets a bit, 0 leaves it
+    ReadClear,   // reading clears the register
+    WriteClear,  // any write clears the register
+    WriteSet,    // any write sets the register
     Unspecified,
 }
 
+impl RegisterAccess {
+    /// from the 'read-write' entry of a register
+    pub fn parse(access_str: &str) -> Option<RegisterAccess> {
+        match access_str {
+            "ro" => Some(RegisterAccess::ReadOnly),
+            "wo" => Some(RegisterAccess::WriteOnly),
+            "w1c" => Some(RegisterAccess::Write1Clear),
+            "w1s" => Some(RegisterAccess::Write1Set),
+            "rc" => Some(RegisterAccess::ReadClear),
+            "wc" => Some(RegisterAccess::WriteClear),
+            "ws" => Some(RegisterAccess::WriteSet),
+            "rw" => Some(RegisterAccess::ReadWrite),
+            "wr" => Some(RegisterAccess::ReadWrite),
+            _ => None,
+        }
+    }
+
+    /// as written in the 'read-write' entry of a register
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegisterAccess::ReadOnly => "ro",
+            RegisterAccess::ReadWrite => "rw",
+            RegisterAccess::WriteOnly => "wo",
+            RegisterAccess::Write1Clear => "w1c",
+            RegisterAccess::Write1Set => "w1s",
+            RegisterAccess::ReadClear => "rc",
+            RegisterAccess::WriteClear => "wc",
+            RegisterAccess::WriteSet => "ws",
+            RegisterAccess::Unspecified => "unspecified",
+        }
+    }
+
+    pub fn is_readable(&self) -> bool {
+        !matches!(self, RegisterAccess::WriteOnly)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        !matches!(self, RegisterAccess::ReadOnly)
+    }
+}
+
+///
+/// Relaxations of the checks made when a RegisterOp is created
+///
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AccessOptions {
+    /// permit reads with side effects, i.e. of read-to-clear registers
+    pub force: bool,
+    /// mask values that are too wide for their field instead of rejecting them
+    pub truncate: bool,
+}
 
 pub struct RegisterOp {
+    pub path: String,
     pub offset: u64,
-    pub set_mask: u32,
-    pub read_mask: u32,
+    pub width: AccessWidth,
+    pub set_mask: u64,
+    pub read_mask: u64,
     pub shift: u32,
-    pub value: Option<u32>,
+    pub value: Option<u64>,
+    pub values: Vec<(String, u64)>,
     shadow_offset: Option<u64>,
     access_type: RegisterAccess,
 }
 
 
-pub fn parse_bits(bitsstr: &str) -> Result<(u32, u32), String> {
+///
+/// Parse a "hi:lo" bit range within a register of width bits into its mask and shift
+///
+pub fn parse_bits(bitsstr: &str, width: u32) -> Result<(u64, u32), String> {
     let parts: Vec<&str> = bitsstr.split(':').collect();
 
     if parts.len() != 2 {
         return Err("Invalid bit range format. Expected 'hi:lo'".parse().unwrap());
     }
 
-    let hi: u32 = match parts[0].trim().parse() {
+    let hi = match parse_value(parts[0]) {
         Ok(n) => n,
-        Err(_) => return Err("Invalid high bit value".parse().unwrap()),
+        Err(e) => return Err(format!("Invalid high bit value: {e}")),
     };
 
-    let lo: u32 = match parts[1].trim().parse() {
+    let lo = match parse_value(parts[1]) {
         Ok(n) => n,
-        Err(_) => return Err("Invalid low bit value".parse().unwrap()),
+        Err(e) => return Err(format!("Invalid low bit value: {e}")),
     };
 
-    if hi >= 32 || lo >= 32 {
-        return Err("Bit positions must be less than 32".parse().unwrap());
+    if hi >= width as u64 || lo >= width as u64 {
+        return Err(format!("Bit positions must be less than {width}"));
     }
 
     if hi < lo {
         return Err("High bit must be greater than or equal to low bit".parse().unwrap());
     }
 
-    let width = (hi - lo) + 1;
-    if width == 32 {
-        return Ok((0xFFFFFFFF as u32, 0u32))
+    let (hi, lo) = (hi as u32, lo as u32);
+    let field_width = (hi - lo) + 1;
+    if field_width == 64 {
+        return Ok((u64::MAX, 0u32))
     }
-    let mask = ((1u32 << width) - 1) << lo;
+    let mask = ((1u64 << field_width) - 1) << lo;
 
     Ok((mask, lo))
 }
 
+///
+/// Parse the symbolic values of a field, given as "name=value, name=value..."
+///
+/// e.g. "input=0b000, output=0b001, alt0=0b100"
+///
+pub fn parse_values(valuesstr: &str) -> Result<Vec<(String, u64)>, String> {
+    let mut values: Vec<(String, u64)> = Vec::new();
+
+    for entry in valuesstr.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = entry.split('=').collect();
+        if parts.len() != 2 || parts[0].trim().is_empty() {
+            return Err(format!("Invalid value entry '{entry}'. Expected 'name=value'"));
+        }
+        let name = parts[0].trim();
+        let value = match parse_value(parts[1]) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("Invalid value for '{name}': {e}")),
+        };
+        if values.iter().any(|(n, _)| n == name) {
+            return Err(format!("Duplicate value name '{name}'"));
+        }
+        values.push((name.to_string(), value));
+    }
+
+    Ok(values)
+}
+
+///
+/// Get the symbolic values of the register at path, empty if it has none
+///
+pub fn get_values(descender: &dyn Descender<dyn Write>, path: &str) -> Result<Vec<(String, u64)>, String> {
+    match descender.get_string_field_or_parent(path, "values") {
+        Ok(v) => parse_values(&v).map_err(|e| format!("Invalid values for register {}: {}", path, e)),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+///
+/// Resolve the right hand side of path=value, which is either one of the register's
+/// symbolic values or a number
+///
+pub fn resolve_value(descender: &dyn Descender<dyn Write>, path: &str, text: &str) -> Result<u64, String> {
+    let values = get_values(descender, path)?;
+    if let Some((_, v)) = values.iter().find(|(n, _)| n == text.trim()) {
+        return Ok(*v);
+    }
+
+    match parse_value(text) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            if values.is_empty() {
+                Err(e)
+            } else {
+                let names: Vec<&str> = values.iter().map(|(n, _)| n.as_str()).collect();
+                Err(format!("unknown value '{}' for {}, expected a number or one of: {}", text, path, names.join(", ")))
+            }
+        }
+    }
+}
+
 impl RegisterOp {
     
     /// a noop register
     pub fn noop() -> RegisterOp {
         RegisterOp {
+            path: String::new(),
             offset: 0,
+            width: AccessWidth::Bits32,
             set_mask: 0,
             read_mask: 0,
             shift: 0,
             value: None,
+            values: Vec::new(),
             shadow_offset: None,
             access_type: RegisterAccess::Unspecified,
         }
     }
-    pub fn new(descender: &dyn Descender<dyn Write>, value: Option<u32>, path:&str) -> Result<RegisterOp, String> {
+
+    ///
+    /// A register already resolved elsewhere, e.g. by generated code, to access with value
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_parts(path: &str, offset: u64, width: AccessWidth, shift: u32, mask: u64,
+                      access_type: RegisterAccess, shadow_offset: Option<u64>, value: Option<u64>) -> RegisterOp {
+        RegisterOp {
+            path: path.to_string(),
+            offset,
+            width,
+            set_mask: !mask,
+            read_mask: mask,
+            shift,
+            value,
+            values: Vec::new(),
+            shadow_offset,
+            access_type,
+        }
+    }
+
+    ///
+    /// Resolve the register at path, following its parents, without checking whether
+    /// it may be read or set
+    ///
+    pub fn resolve(descender: &dyn Descender<dyn Write>, path: &str) -> Result<RegisterOp, String> {
 
         let offset_r = descender.get_int_field_or_parent(path, "offset");
         let offset = match offset_r {
@@ -101,10 +258,22 @@ impl RegisterOp {
             Err(e) => return Err(format!("Invalid offset for register {}: {}", path, e)),
         } ;
 
+        let width = match descender.get_int_field_or_parent(path, "width") {
+            Ok(w) => match AccessWidth::from_bits(w as u64) {
+                Ok(w) => w,
+                Err(e) => return Err(format!("{} for register {}", e, path)),
+            },
+            Err(_) => AccessWidth::Bits32,
+        } ;
+
         let bits_r = descender.get_string_field_or_parent(path, "bits");
-        let bits = bits_r.unwrap_or_else(|_| "31:0".to_string());
+        let bits = bits_r.unwrap_or_else(|_| format!("{}:0", width.bits() - 1));
+
+        if !(offset as u64).is_multiple_of(width.bytes()) {
+            return Err(format!("Offset 0x{:X} of register {} is not aligned to its {} byte width", offset, path, width.bytes()));
+        }
 
-        let mask_r = parse_bits(&*bits) ;
+        let mask_r = parse_bits(&bits, width.bits()) ;
         let (mask, shift) = match mask_r {
             Ok((m, s)) => (m, s),
             Err(e) => return Err(e)
@@ -113,21 +282,13 @@ impl RegisterOp {
 
         let read_only_r = descender.get_string_field_or_parent(path, "read-write");
         let access_type = match read_only_r {
-            Ok(access_str) => match access_str.as_str() {
-                "ro" => RegisterAccess::ReadOnly,
-                "wo" => RegisterAccess::WriteOnly,
-                "w1c" => RegisterAccess::Write1Clear,
-                "rw" => RegisterAccess::ReadWrite,
-                "wr" => RegisterAccess::ReadWrite,
-                _ => return Err(format!("Invalid read-write value '{}' for register {} must be ro, rw, wo or w1c", access_str, path))
+            Ok(access_str) => match RegisterAccess::parse(access_str.as_str()) {
+                Some(a) => a,
+                None => return Err(format!("Invalid read-write value '{}' for register {} must be ro, rw, wo, w1c, w1s, rc, wc or ws", access_str, path))
             },
             Err(_) => RegisterAccess::Unspecified,
         };
 
-        if access_type == RegisterAccess::ReadOnly && value.is_some() {
-            return Err(format!("Register {} is read only and cannot be set", path));
-        }
-
         let shadow_path = descender.get_string_field_or_parent(path, "shadow");
         let shadow_offset = match shadow_path {
             Err(_) => None, // no shadow reg found
@@ -137,65 +298,184 @@ impl RegisterOp {
             }
         } ;
 
-
-        if access_type == RegisterAccess::WriteOnly && !value.is_some() && shadow_offset.is_none() {
-            return Err(format!("Register {} is write only and cannot be read", path));
-        }
-
-        match value {
-            None => (),
-            Some(v) => {
-                if v > (0x01 << shift) {
-                    return Err(format!("Value {} is out of range for register {}", v, path));
-                }
-            }
-        }
+        let values = get_values(descender, path)?;
 
         Ok(RegisterOp {
+            path: path.to_string(),
             offset: offset as u64,
+            width,
             set_mask: !mask,
             read_mask: mask,
-            shift: shift,
-            access_type: access_type,
-            value: value,
-            shadow_offset: shadow_offset,
+            shift,
+            access_type,
+            value: None,
+            values,
+            shadow_offset,
         })
     }
 
+    pub fn new(descender: &dyn Descender<dyn Write>, value: Option<u64>, path:&str, options: &AccessOptions) -> Result<RegisterOp, String> {
+        let mut op = RegisterOp::resolve(descender, path)?;
+
+        if op.access_type == RegisterAccess::ReadOnly && value.is_some() {
+            return Err(format!("Register {} is read only and cannot be set", path));
+        }
+
+        if op.access_type == RegisterAccess::WriteOnly && value.is_none() && op.shadow_offset.is_none() {
+            return Err(format!("Register {} is write only and cannot be read", path));
+        }
+
+        if op.access_type == RegisterAccess::ReadClear && value.is_none() && op.shadow_offset.is_none() && !options.force {
+            return Err(format!("Register {} is cleared by reading, it is only read when forced", path));
+        }
+
+        let field_max = op.field_max();
+        op.value = match value {
+            None => None,
+            Some(v) if v <= field_max => Some(v),
+            Some(v) if options.truncate => Some(v & field_max),
+            Some(v) => return Err(format!("Value {} (0x{:X}) is out of range for register {}, must be 0 to {} (0x{:X})",
+                                          v, v, path, field_max, field_max)),
+        } ;
+
+        Ok(op)
+    }
+
+    ///
+    /// Check the register, and its shadow, lie within a mapping of length bytes
+    ///
+    pub fn check_bounds(&self, length: u64) -> Result<(), String> {
+        let bytes = self.width.bytes();
+        if self.offset.checked_add(bytes).is_none_or(|end| end > length) {
+            return Err(format!("Register {} at offset 0x{:X} + {} bytes is beyond the mapped length 0x{:X}",
+                               self.path, self.offset, bytes, length));
+        }
+        if let Some(shadow) = self.shadow_offset.filter(|s| s.checked_add(bytes).is_none_or(|end| end > length)) {
+            return Err(format!("Shadow of register {} at offset 0x{:X} + {} bytes is beyond the mapped length 0x{:X}",
+                               self.path, shadow, bytes, length));
+        }
+        Ok(())
+    }
 
-    pub fn set(&self, addr: *mut u8) -> Result<u32, String> {
+    /// the offset reads are made from, the shadow's if it has one
+    pub fn read_offset(&self) -> u64 {
+        self.shadow_offset.unwrap_or(self.offset)
+    }
+
+    pub fn shadow_offset(&self) -> Option<u64> {
+        self.shadow_offset
+    }
+
+    /// largest value the field can hold
+    pub fn field_max(&self) -> u64 {
+        self.read_mask >> self.shift
+    }
+
+    /// (hi, lo) bit positions of the field
+    pub fn bits(&self) -> (u32, u32) {
+        (self.shift + self.field_width() - 1, self.shift)
+    }
+
+    pub fn access_type(&self) -> RegisterAccess {
+        self.access_type
+    }
+
+    ///
+    /// Why reading the register would fail or disturb it, none when it may be read.  A
+    /// shadowed register is read from its shadow and rc registers are read with force.
+    ///
+    pub fn read_hazard(&self, force: bool) -> Option<&'static str> {
+        if self.shadow_offset.is_some() {
+            return None;
+        }
+        match self.access_type {
+            RegisterAccess::WriteOnly => Some("write-only"),
+            RegisterAccess::ReadClear if !force => Some("cleared by reading"),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Why writing a value back to the register would do more than set it, none when it
+    /// holds what is written
+    ///
+    pub fn write_hazard(&self) -> Option<&'static str> {
+        match self.access_type {
+            RegisterAccess::ReadOnly => Some("read-only"),
+            RegisterAccess::Write1Clear => Some("writing 1 clears"),
+            RegisterAccess::Write1Set => Some("writing 1 sets"),
+            RegisterAccess::ReadClear => Some("cleared by reading"),
+            RegisterAccess::WriteClear => Some("any write clears"),
+            RegisterAccess::WriteSet => Some("any write sets"),
+            RegisterAccess::ReadWrite | RegisterAccess::WriteOnly | RegisterAccess::Unspecified => None,
+        }
+    }
+
+    /// number of bits in the field
+    pub fn field_width(&self) -> u32 {
+        self.read_mask.count_ones()
+    }
+
+    /// the symbolic name for a field value, if it has one
+    pub fn label(&self, value: u64) -> Option<&str> {
+        self.values.iter().find(|(_, v)| *v == value).map(|(n, _)| n.as_str())
+    }
+
+
+    pub fn set(&self, mapping: &Mapping) -> Result<u64, String> {
+        self.set_word(mapping)?;
+        Ok(self.value.expect("set with no value"))
+    }
+
+    ///
+    /// Set the field to its value, returning the whole word written
+    ///
+    pub fn set_word(&self, mapping: &Mapping) -> Result<u64, String> {
 
         let read_offset = match self.shadow_offset {
             None => self.offset,
             Some(o) => o,
         } ;
 
-        let addr_read = unsafe { addr.add(read_offset as usize) };
-        
-        let addr_write = unsafe { addr.add(self.offset as usize) };
         let value = match self.value {
             None => panic!("set with no value"), // panic appropriate
             Some(v) => v,
         } ;
 
         let bits = value << self.shift;
-        unsafe {
-            let curr_value = *(addr_read as *mut u32);
-            let new_value = (curr_value & self.set_mask) | bits;
-            *(addr_write as *mut u32) = new_value ;
-        }
-        Ok(value)
-    }
 
-    pub fn get(&self, addr: *mut u8) -> u32 {
+        /*
+         * Only registers that hold their value are read-modify-written.  For the
+         * others the other bits are written as 0 so a w1c write doesn't clear
+         * every pending bit in the register and a read doesn't clear an rc one.
+         */
+        let read_modify_write = match self.access_type {
+            RegisterAccess::ReadWrite | RegisterAccess::Unspecified => true,
+            RegisterAccess::WriteOnly => self.shadow_offset.is_some(),
+            _ => false,
+        } ;
 
-        let offset = match self.shadow_offset {
-            None => self.offset,
-            Some(o) => o,
+        let new_value = if read_modify_write {
+            let curr_value = mapping.read(read_offset, self.width)?;
+            (curr_value & self.set_mask) | bits
+        } else {
+            bits
         } ;
+        mapping.write(self.offset, self.width, new_value)?;
+        Ok(new_value)
+    }
+
+    pub fn get(&self, mapping: &Mapping) -> Result<u64, String> {
+        Ok(self.field_of(self.get_word(mapping)?))
+    }
+
+    /// read the whole word holding the field, from its shadow if it has one
+    pub fn get_word(&self, mapping: &Mapping) -> Result<u64, String> {
+        mapping.read(self.read_offset(), self.width)
+    }
 
-        let addr2 = unsafe { addr.add(offset as usize) };
-        let value = unsafe { *(addr2 as *mut u32) };
-        (value & self.read_mask) >> self.shift
+    /// the field's value within word
+    pub fn field_of(&self, word: u64) -> u64 {
+        (word & self.read_mask) >> self.shift
     }
 }
\ No newline at end of file
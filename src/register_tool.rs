@@ -1,5 +1,6 @@
-use crate::register_op::RegisterOp;
-use crate::unsafes::mmap_memory;
+use crate::register_op::{get_values, parse_bits, resolve_value, AccessOptions, RegisterOp};
+use crate::register_map::{region_block, word_of, Region, RegionKeys};
+use crate::unsafes::{page_size, page_span, Mapping};
 use aep_rust_common::descender::Descender;
 //
 // SPDX-License-Identifier: MIT
@@ -26,14 +27,28 @@ use aep_rust_common::descender::Descender;
 //
 use std::io::Write;
 
+///
+/// What an access of a register read or wrote
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Access {
+    /// the field's value
+    pub value: u64,
+    /// the whole word read, or written
+    pub word: u64,
+    /// the physical address of the register
+    pub address: u64,
+}
+
 pub struct RegisterTool {
     descender: Box<dyn Descender<dyn Write>>,
-    regs: Vec<RegisterOp>,
-    addr: *mut u8,
-    test_mode: bool,
-    device: String,
-    base: u64,
-    length: u64,
+    /// the registers to access and the index of the region each is in
+    regs: Vec<(RegisterOp, usize)>,
+    options: AccessOptions,
+    /// the file's 'device', 'base' and 'length', the defaults for every block
+    file: RegionKeys,
+    /// the regions of the gathered registers, mapped when the registers are applied
+    regions: Vec<(Region, Option<Mapping>)>,
 }
 
 impl RegisterTool {
@@ -68,8 +83,24 @@ impl RegisterTool {
             println!("   width: {}", width) ;
             println!("   bits: {}", bits) ;
             println!("   description: \"{}\"", desc) ;
+            match get_values(&*self.descender, path) {
+                Ok(values) => {
+                    let field_width = parse_bits(&bits, width as u32).map(|(m, _)| m.count_ones()).unwrap_or(width as u32) as usize;
+                    if !values.is_empty() {
+                        println!("   values:") ;
+                    }
+                    for (name, v) in values {
+                        println!("      {name}: 0b{v:0field_width$b}") ;
+                    }
+                }
+                Err(e) => {
+                    println!("   values: {e}") ;
+                    fail = true ;
+                    bad_regs.push(path) ;
+                }
+            }
         }
-        match self.descender.set_root(&*old_root) {
+        match self.descender.set_root(&old_root) {
             Ok(_) => {},
             Err(e) => {
                 eprintln!("Error resetting root: {}", e);
@@ -87,63 +118,67 @@ impl RegisterTool {
 }
 
 impl RegisterTool {
-    pub fn new(descender: Box<dyn Descender<dyn Write>>) -> Result<Self, Vec<String>> {
-        let mut errs: Vec<String> = Vec::new();
-
+    pub fn new(descender: Box<dyn Descender<dyn Write>>) -> Self {
         /*
-         * Validate the integrity of the file
+         * The file's device, base and length are the defaults for every top level
+         * block.  They are optional when the blocks give their own, a register of a
+         * block with neither fails to gather.
          */
-        let device = match descender.get_string_field_or_parent("","device") {
-            Ok(d) => d,
-            Err(e) => {
-                errs.push(format!("device not found: {}", e));
-                "".to_string()
-            }
-        } ;
-
-        let base = match descender.get_int_field_or_parent("","base") {
-            Ok(b) => b,
-            Err(e) => {
-                errs.push(format!("base not found: {}", e));
-                0
-            }
-        }  as u64 ;
-
-        let length = match descender.get_int_field_or_parent("","length") {
-            Ok(l) => l,
-            Err(e) => {
-                errs.push(format!("length not found: {}", e));
-                0
-            }
-        } as u64 ;
+        let file = RegionKeys::read(&*descender, "") ;
+        Self {descender, regs: Vec::new(), options: AccessOptions::default(), file, regions: Vec::new() }
+    }
 
-        /*
-         * return all collected errors
-         */
-        if errs.len() > 0 {
-            return Err(errs);
+    ///
+    /// Map the regions of the gathered registers, only those blocks that are used
+    ///
+    pub fn set_base_address(&mut self) -> Result<(), String> {
+        for (region, mapping) in self.regions.iter_mut().filter(|(_, m)| m.is_none()) {
+            *mapping = match Mapping::device(region.device.as_str(), region.base, region.length) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    return Err(format!("Error mapping memory: {}", e));
+                }
+            } ;
         }
-        let register_tool = Self {descender, regs: Vec::new(), addr: std::ptr::null_mut(), test_mode: false, device, base, length } ;
+        Ok(())
+    }
 
-        Ok(register_tool)
+    ///
+    /// The physical memory mapped for the registers, each region's base and length
+    /// rounded out to whole pages, as its device, start and end
+    ///
+    pub fn physical_ranges(&self) -> Vec<(&str, u64, u64)> {
+        self.regions.iter().map(|(region, _)| {
+            let (start, span, _) = page_span(region.base, region.length, page_size());
+            (region.device.as_str(), start, start + span)
+        }).collect()
     }
 
-    pub fn set_base_address(&mut self) -> Result<(), String> {
-        self.addr = match mmap_memory(self.device.as_str(), self.base, self.length) {
-            Ok(a) => a,
-            Err(e) => {
-                return Err(format!("Error mapping memory: {}", e));
-            }
-        } ;
-        Ok(())
+    pub fn set_options(&mut self, options: AccessOptions) {
+        self.options = options;
     }
 
     pub fn set_test_area(&mut self) {
-        let mut memory = Vec::with_capacity(self.length as usize);
-        memory.resize(self.length as usize, 0u8);
-        self.addr = memory.as_mut_ptr();
-        std::mem::forget(memory);
-        self.test_mode = true;
+        for (region, mapping) in self.regions.iter_mut() {
+            *mapping = Some(Mapping::buffer(region.length));
+        }
+    }
+
+    ///
+    /// The index of the region of the block path's word is in, added if it's the first
+    /// register of it.  Blocks that give no region of their own share the file's.
+    ///
+    fn region_of(&mut self, path: &str) -> Result<usize, String> {
+        let word = word_of(path, |p| self.descender.get_string_field_or_parent(p, "parent").ok());
+        let block = region_block(&word, |b| !RegionKeys::read(&*self.descender, b).is_empty());
+        let region = RegionKeys::within(&*self.descender, block).region(&self.file, block)?;
+        match self.regions.iter().position(|(r, _)| *r == region) {
+            Some(i) => Ok(i),
+            None => {
+                self.regions.push((region, None));
+                Ok(self.regions.len() - 1)
+            }
+        }
     }
 
 
@@ -168,26 +203,35 @@ impl RegisterTool {
             }
             let is_set = parts.len() == 2 ;
             let value = if is_set {
-                match parts[1].parse::<u32>() {
+                match resolve_value(&*self.descender, parts[0], parts[1]) {
                     Ok(v) => Some(v),
-                    Err(_) => {
-                        errs.push(format!("Bad argument {}", spec)) ;
-                        None
+                    Err(e) => {
+                        errs.push(format!("Bad argument {}: {}", spec, e)) ;
+                        continue ;
                     }
                 }
             } else { None };
 
-            let r = match RegisterOp::new(&*self.descender, value, parts[0]) {
+            let region = match self.region_of(parts[0]) {
+                Ok(i) => i,
+                Err(e) => {
+                    errs.push(e) ;
+                    continue ;
+                }
+            } ;
+            let length = self.regions[region].0.length ;
+            let r = match RegisterOp::new(&*self.descender, value, parts[0], &self.options)
+                .and_then(|r| r.check_bounds(length).map(|_| r)) {
                 Ok(r) => r,
                 Err(e) => { errs.push(e) ;
                     RegisterOp::noop()
                 }
             } ;
 
-            self.regs.push(r)
+            self.regs.push((r, region))
         }
 
-        match self.descender.set_root(&*old_root) {
+        match self.descender.set_root(&old_root) {
             Ok(_) => {},
             Err(e) => {
                 println!("Error resetting root: {}", e);
@@ -195,42 +239,43 @@ impl RegisterTool {
             }
         } ;
 
-        if errs.len() > 0 {
+        if !errs.is_empty() {
             Err(errs)
         } else {
             Ok(())
         }
     }
 
-    pub fn apply_registers<F>(&self, f: F) -> Result<Vec<Result<u32, String>>, String>
+    pub fn apply_registers<F>(&self, f: F) -> Result<Vec<Result<u64, String>>, String>
     where
-        F: Fn(u32) -> Result<u32, String>,
+        F: Fn(&RegisterOp, u64) -> Result<u64, String>,
     {
-        let mut results : Vec<Result<u32, String>> = Vec::new();
-        for reg in &self.regs {
-            if !reg.value.is_some() {
-                results.push(f(reg.get(self.addr)));
-            }
-            else {
-                results.push(match reg.set(self.addr) {
-                    Ok(i) => f(i),
-                    Err(e) => Err(e),
-                });
-            }
-        }
-        Ok(results)
+        self.access_registers(|reg, access| f(reg, access.value))
     }
-}
 
-///
-/// Free any memory we might have allocated
-///
-impl Drop for RegisterTool {
-    fn drop(&mut self) {
-        if self.test_mode {
-            unsafe {
-                std::ptr::drop_in_place(self.addr);
-            }
+    ///
+    /// Read or set each register as apply_registers does, passing f the whole of what
+    /// each access read or wrote
+    ///
+    pub fn access_registers<F>(&self, f: F) -> Result<Vec<Result<u64, String>>, String>
+    where
+        F: Fn(&RegisterOp, &Access) -> Result<u64, String>,
+    {
+        let mut results : Vec<Result<u64, String>> = Vec::new();
+        for (reg, region) in &self.regs {
+            let (region, mapping) = match &self.regions[*region] {
+                (r, Some(m)) => (r, m),
+                (_, None) => return Err("Register memory is not mapped".to_string()),
+            } ;
+            let access = match reg.value {
+                None => reg.get_word(mapping).map(|word| (reg.field_of(word), word)),
+                Some(v) => reg.set_word(mapping).map(|word| (v, word)),
+            } ;
+            results.push(match access {
+                Ok((value, word)) => f(reg, &Access { value, word, address: region.base + reg.offset }),
+                Err(e) => Err(e),
+            });
         }
+        Ok(results)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,152 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Snapshots of register values, saved to return the hardware to a known state after
+//! experimenting.  A snapshot records the words read and the register map they were
+//! read with, so it is only restored onto the same map at the same addresses.
+//!
+//! ```yaml
+//! config-hash: "5c1b2f0e9a7d3346"
+//! registers:
+//!   - {path: GPIO.words.function0, address: 0x7E200000, word: 0x00000000}
+//! ```
+//!
+use yaml_rust::{Yaml, YamlEmitter};
+use crate::config::load_document;
+use crate::register_map::RegisterMap;
+use crate::value::parse_value;
+
+///
+/// A word saved in a snapshot
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedRegister {
+    pub path: String,
+    pub address: u64,
+    pub word: u64,
+}
+
+///
+/// The words of a register map read at one time
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// config_hash of the register file the words were read with
+    pub hash: String,
+    /// in the order read, that of the register file, which is the order restored
+    pub registers: Vec<SavedRegister>,
+}
+
+///
+/// A hash of the register map in doc, a composed register file.  It is FNV-1a of the
+/// document as YAML, so it is the same however the file is split or formatted.
+///
+pub fn config_hash(doc: &Yaml) -> Result<String, String> {
+    let mut text = String::new();
+    YamlEmitter::new(&mut text).dump(doc).map_err(|e| format!("{e:?}"))?;
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    Ok(format!("{hash:016x}"))
+}
+
+/// a number of the snapshot, a YAML integer or a hex string too large for one
+fn number(node: &Yaml, what: &str) -> Result<u64, String> {
+    match node {
+        Yaml::Integer(i) if *i >= 0 => Ok(*i as u64),
+        Yaml::String(s) => parse_value(s).map_err(|e| format!("{what}: {e}")),
+        _ => Err(format!("{what} missing or not a number")),
+    }
+}
+
+impl Snapshot {
+    /// the snapshot as YAML
+    pub fn to_yaml(&self) -> String {
+        let mut text = format!("config-hash: \"{}\"\nregisters:\n", self.hash);
+        for r in &self.registers {
+            text.push_str(&format!("  - {{path: {}, address: 0x{:X}, word: 0x{:X}}}\n", r.path, r.address, r.word));
+        }
+        text
+    }
+
+    pub fn parse(content: &str) -> Result<Snapshot, String> {
+        let doc = load_document(content)?;
+        let hash = doc["config-hash"].as_str().ok_or("config-hash missing from snapshot")?.to_string();
+        let entries = doc["registers"].as_vec().ok_or("registers missing from snapshot")?;
+        let mut registers: Vec<SavedRegister> = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let path = entry["path"].as_str().ok_or(format!("registers[{i}].path missing"))?;
+            registers.push(SavedRegister {
+                path: path.to_string(),
+                address: number(&entry["address"], &format!("{path} address"))?,
+                word: number(&entry["word"], &format!("{path} word"))?,
+            });
+        }
+        Ok(Snapshot { hash, registers })
+    }
+
+    ///
+    /// Check the snapshot was saved from map, whose file hashes to hash, with every
+    /// register where it is now, returning every difference found
+    ///
+    pub fn check(&self, map: &RegisterMap, hash: &str) -> Result<(), Vec<String>> {
+        let mut errs: Vec<String> = Vec::new();
+        if self.hash != hash {
+            errs.push(format!("The register map has changed since the snapshot was saved, config-hash {} is now {}",
+                              self.hash, hash));
+        }
+        for r in &self.registers {
+            match map.get(&r.path) {
+                None => errs.push(format!("{} is no longer in the register map", r.path)),
+                Some(def) => {
+                    let address = map.region(map.block(&r.path)).base + def.op.offset;
+                    if address != r.address {
+                        errs.push(format!("{} has moved from 0x{:X} to 0x{:X}", r.path, r.address, address));
+                    }
+                }
+            }
+        }
+        if errs.is_empty() { Ok(()) } else { Err(errs) }
+    }
+
+    ///
+    /// The "path=word" settings that restore the snapshot, in its order, and the
+    /// registers that are not restored with why.  Registers that a write would do more
+    /// than set, such as w1c ones, are not written, nor are those saved from their
+    /// shadow, whose word is the shadow's, e.g. the levels of a set/clear pair.
+    ///
+    pub fn restore_plan(&self, map: &RegisterMap) -> (Vec<String>, Vec<(String, &'static str)>) {
+        let mut settings: Vec<String> = Vec::new();
+        let mut skipped: Vec<(String, &'static str)> = Vec::new();
+        for r in &self.registers {
+            let hazard = map.get(&r.path).and_then(|def| match def.op.shadow_offset() {
+                Some(_) => Some("saved from its shadow"),
+                None => def.op.write_hazard(),
+            });
+            match hazard {
+                Some(reason) => skipped.push((r.path.clone(), reason)),
+                None => settings.push(format!("{}=0x{:X}", r.path, r.word)),
+            }
+        }
+        (settings, skipped)
+    }
+}
@@ -0,0 +1,643 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Import of CMSIS-SVD register descriptions.
+//!
+//! An SVD device becomes the same tree a register file describes: each peripheral is a
+//! block under 'registers', each register a node with an offset and each field a node
+//! beneath its register with 'parent' pointing back at it, so that
+//! `USART1.CR1.UE` addresses the UE field of USART1's CR1 register.
+//!
+use std::collections::HashMap;
+use roxmltree::{Document, Node};
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+use crate::value::parse_value;
+use crate::register_map::{identifier, RegisterDef, RegisterMap};
+use crate::register_op::RegisterAccess;
+
+/// value as a YAML integer, or a hex string when it is too large for one
+fn yaml_number(value: u64) -> Yaml {
+    match i64::try_from(value) {
+        Ok(i) => Yaml::Integer(i),
+        Err(_) => Yaml::String(format!("0x{value:X}")),
+    }
+}
+
+/// Is this content an SVD document
+pub fn is_svd(content: &str) -> bool {
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    start.starts_with('<') && start.contains("<device")
+}
+
+///
+/// Properties that SVD lets a device, peripheral, cluster or register give its children
+///
+#[derive(Clone, Default)]
+struct Defaults {
+    size: Option<u64>,
+    access: Option<String>,
+    reset: Option<u64>,
+}
+
+///
+/// An SVD element along with the elements it is derivedFrom, nearest first
+///
+struct Element<'a, 'i> {
+    chain: Vec<Node<'a, 'i>>,
+}
+
+impl<'a, 'i> Element<'a, 'i> {
+    fn child(&self, tag: &str) -> Option<Node<'a, 'i>> {
+        self.chain.iter().find_map(|n| child(*n, tag))
+    }
+
+    fn text(&self, tag: &str) -> Option<&'a str> {
+        self.chain.iter().find_map(|n| text(*n, tag))
+    }
+
+    fn number(&self, tag: &str) -> Result<Option<u64>, String> {
+        match self.text(tag) {
+            None => Ok(None),
+            Some(t) => parse_svd_number(t).map(Some).map_err(|e| format!("invalid {tag}: {e}")),
+        }
+    }
+
+    fn node(&self) -> Node<'a, 'i> {
+        self.chain[0]
+    }
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, tag: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|c| c.is_element() && c.tag_name().name() == tag)
+}
+
+fn children<'a, 'i>(node: Node<'a, 'i>, tag: &'static str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(move |c| c.is_element() && c.tag_name().name() == tag)
+}
+
+fn text<'a>(node: Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    child(node, tag).and_then(|c| c.text()).map(|t| t.trim())
+}
+
+///
+/// Parse an SVD scaledNonNegativeInteger, which beyond the forms parse_value accepts
+/// may be binary written '#0101' and carry a k, M or G suffix
+///
+pub fn parse_svd_number(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if let Some(binary) = text.strip_prefix('#') {
+        if binary.contains(['x', 'X']) {
+            return Err(format!("\"{text}\" has don't care bits"));
+        }
+        return parse_value(&format!("0b{binary}"));
+    }
+
+    // none of the suffixes are hex digits so they can't be confused with one
+    let (digits, scale) = match text.chars().last() {
+        Some('k') | Some('K') => (&text[..text.len() - 1], 1u64 << 10),
+        Some('m') | Some('M') => (&text[..text.len() - 1], 1u64 << 20),
+        Some('g') | Some('G') => (&text[..text.len() - 1], 1u64 << 30),
+        _ => (text, 1),
+    };
+    parse_value(digits)?.checked_mul(scale).ok_or_else(|| format!("\"{text}\" does not fit in 64 bits"))
+}
+
+fn description(element: &Element) -> Option<String> {
+    element.text("description").map(|d| d.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+///
+/// The read-write value for SVD access, modifiedWriteValues and readAction
+///
+fn read_write(access: Option<&str>, modified: Option<&str>, read_action: Option<&str>) -> Option<String> {
+    if read_action == Some("clear") {
+        return Some("rc".to_string());
+    }
+    let rw = match modified {
+        Some("oneToClear") => Some("w1c"),
+        Some("oneToSet") => Some("w1s"),
+        Some("clear") => Some("wc"),
+        Some("set") => Some("ws"),
+        _ => match access {
+            Some("read-only") => Some("ro"),
+            Some("write-only") | Some("writeOnce") => Some("wo"),
+            Some("read-write") | Some("read-writeOnce") => Some("rw"),
+            _ => None,
+        },
+    };
+    rw.map(|s| s.to_string())
+}
+
+///
+/// The names and (zero based) index of each instance of a possibly dim'ed element
+///
+/// Returns the key to store the element under, whether the instances form an array
+/// (name[%s]) and the name of each instance
+///
+fn dim_instances(element: &Element, name: &str) -> Result<(String, bool, Vec<String>), String> {
+    let dim = match element.number("dim")? {
+        None => return Ok((name.to_string(), false, vec![name.to_string()])),
+        Some(d) => d as usize,
+    };
+
+    if name.contains("[%s]") {
+        let key = name.replace("[%s]", "");
+        return Ok((key.clone(), true, (0..dim).map(|i| format!("{key}[{i}]")).collect()));
+    }
+
+    let indices: Vec<String> = match element.text("dimIndex") {
+        None => (0..dim).map(|i| i.to_string()).collect(),
+        Some(index) if index.contains('-') && !index.contains(',') => {
+            let parts: Vec<&str> = index.split('-').collect();
+            match (parts[0].trim().parse::<u64>(), parts[1].trim().parse::<u64>()) {
+                (Ok(lo), Ok(hi)) => (lo..=hi).map(|i| i.to_string()).collect(),
+                _ => {
+                    // letter ranges, e.g. A-D
+                    let lo = parts[0].trim().chars().next().unwrap_or('A');
+                    let hi = parts[1].trim().chars().next().unwrap_or('A');
+                    (lo..=hi).map(|c| c.to_string()).collect()
+                }
+            }
+        }
+        Some(index) => index.split(',').map(|i| i.trim().to_string()).collect(),
+    };
+    if indices.len() != dim {
+        return Err(format!("dimIndex of {name} has {} entries, dim is {dim}", indices.len()));
+    }
+    let names: Vec<String> = indices.iter().map(|i| name.replace("%s", i)).collect();
+    Ok((name.to_string(), false, names))
+}
+
+struct Importer<'a, 'i> {
+    enums: HashMap<String, Node<'a, 'i>>,
+}
+
+impl<'a, 'i> Importer<'a, 'i> {
+    ///
+    /// Follow derivedFrom, which names a sibling or gives a dotted path from the device
+    ///
+    fn element(&self, node: Node<'a, 'i>, siblings: &[Node<'a, 'i>], device: Node<'a, 'i>) -> Result<Element<'a, 'i>, String> {
+        let mut chain = vec![node];
+        let mut current = node;
+        while let Some(base_name) = current.attribute("derivedFrom") {
+            let base = match siblings.iter().find(|s| text(**s, "name") == Some(base_name)) {
+                Some(b) => *b,
+                None => find_path(device, base_name)
+                    .ok_or_else(|| format!("derivedFrom {base_name} not found for {}", text(node, "name").unwrap_or("?")))?,
+            };
+            if chain.contains(&base) {
+                return Err(format!("derivedFrom loop at {base_name}"));
+            }
+            chain.push(base);
+            current = base;
+        }
+        Ok(Element { chain })
+    }
+
+    fn defaults(&self, element: &Element, parent: &Defaults) -> Result<Defaults, String> {
+        Ok(Defaults {
+            size: element.number("size")?.or(parent.size),
+            access: element.text("access").map(|s| s.to_string()).or(parent.access.clone()),
+            reset: element.number("resetValue")?.or(parent.reset),
+        })
+    }
+
+    ///
+    /// Import the registers and clusters below node into hash, returning the end of the
+    /// highest register
+    ///
+    fn registers(&self, hash: &mut Hash, node: Node<'a, 'i>, path: &str, offset: u64, defaults: &Defaults, device: Node<'a, 'i>) -> Result<u64, String> {
+        let siblings: Vec<Node> = node.children().filter(|c| c.is_element()).collect();
+        let mut end = 0;
+
+        for reg_node in siblings.iter().filter(|c| matches!(c.tag_name().name(), "register" | "cluster")) {
+            let element = self.element(*reg_node, &siblings, device)?;
+            let name = element.text("name").ok_or_else(|| format!("{} without a name in {path}", reg_node.tag_name().name()))?;
+            let reg_defaults = self.defaults(&element, defaults)?;
+            let address_offset = element.number("addressOffset")?.unwrap_or(0);
+            let increment = element.number("dimIncrement")?.unwrap_or(0);
+            let (key, is_array, names) = dim_instances(&element, name)?;
+
+            let mut instances: Vec<Yaml> = Vec::new();
+            for (i, instance_name) in names.iter().enumerate() {
+                let reg_path = format!("{path}.{instance_name}");
+                let reg_offset = offset + address_offset + i as u64 * increment;
+                let mut reg = Hash::new();
+
+                if reg_node.tag_name().name() == "cluster" {
+                    if let Some(d) = description(&element) {
+                        reg.insert(Yaml::String("description".into()), Yaml::String(d));
+                    }
+                    // a derived cluster without registers of its own uses its base's
+                    let source = element.chain.iter().find(|n| child(**n, "register").is_some() || child(**n, "cluster").is_some())
+                        .copied().unwrap_or(element.node());
+                    end = end.max(self.registers(&mut reg, source, &reg_path, reg_offset, &reg_defaults, device)?);
+                } else {
+                    let size = reg_defaults.size.unwrap_or(32);
+                    reg.insert(Yaml::String("offset".into()), Yaml::Integer(reg_offset as i64));
+                    reg.insert(Yaml::String("width".into()), Yaml::Integer(size as i64));
+                    let rw = read_write(reg_defaults.access.as_deref(), element.text("modifiedWriteValues"), element.text("readAction"));
+                    if let Some(rw) = rw {
+                        reg.insert(Yaml::String("read-write".into()), Yaml::String(rw));
+                    }
+                    if let Some(reset) = reg_defaults.reset {
+                        reg.insert(Yaml::String("reset".into()), yaml_number(reset));
+                    }
+                    if let Some(d) = description(&element) {
+                        reg.insert(Yaml::String("description".into()), Yaml::String(d));
+                    }
+                    if let Some(fields) = element.child("fields") {
+                        self.fields(&mut reg, fields, &reg_path, device)?;
+                    }
+                    end = end.max(reg_offset + size / 8);
+                }
+                instances.push(Yaml::Hash(reg));
+            }
+
+            if is_array {
+                hash.insert(Yaml::String(key), Yaml::Array(instances));
+            } else {
+                for (instance_name, reg) in names.into_iter().zip(instances) {
+                    hash.insert(Yaml::String(instance_name), reg);
+                }
+            }
+        }
+        Ok(end)
+    }
+
+    fn fields(&self, hash: &mut Hash, fields: Node<'a, 'i>, reg_path: &str, device: Node<'a, 'i>) -> Result<(), String> {
+        let siblings: Vec<Node> = children(fields, "field").collect();
+
+        for field_node in &siblings {
+            let element = self.element(*field_node, &siblings, device)?;
+            let name = element.text("name").ok_or_else(|| format!("field without a name in {reg_path}"))?;
+            let (lsb, msb) = field_bits(&element).map_err(|e| format!("{reg_path}.{name}: {e}"))?;
+            let increment = element.number("dimIncrement")?.unwrap_or(0);
+            let (key, is_array, names) = dim_instances(&element, name)?;
+
+            let mut instances: Vec<Yaml> = Vec::new();
+            for i in 0..names.len() {
+                let shift = i as u64 * increment;
+                let mut field = Hash::new();
+                field.insert(Yaml::String("parent".into()), Yaml::String(reg_path.to_string()));
+                field.insert(Yaml::String("bits".into()), Yaml::String(format!("{}:{}", msb + shift, lsb + shift)));
+                let rw = read_write(element.text("access"), element.text("modifiedWriteValues"), element.text("readAction"));
+                if let Some(rw) = rw {
+                    field.insert(Yaml::String("read-write".into()), Yaml::String(rw));
+                }
+                if let Some(d) = description(&element) {
+                    field.insert(Yaml::String("description".into()), Yaml::String(d));
+                }
+                let values = self.values(&element)?;
+                if !values.is_empty() {
+                    field.insert(Yaml::String("values".into()), Yaml::String(values));
+                }
+                instances.push(Yaml::Hash(field));
+            }
+
+            if is_array {
+                hash.insert(Yaml::String(key), Yaml::Array(instances));
+            } else {
+                for (instance_name, field) in names.into_iter().zip(instances) {
+                    hash.insert(Yaml::String(instance_name), field);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// The field's enumeratedValues as a values string, "name=value, ..."
+    ///
+    fn values(&self, element: &Element<'a, 'i>) -> Result<String, String> {
+        let mut entries: Vec<String> = Vec::new();
+        let mut names: Vec<&str> = Vec::new();
+
+        let node = match element.chain.iter().find(|n| child(**n, "enumeratedValues").is_some()) {
+            Some(n) => *n,
+            None => return Ok(String::new()),
+        };
+        for enums in children(node, "enumeratedValues") {
+            let enums = match enums.attribute("derivedFrom") {
+                Some(base) => {
+                    let key = base.rsplit('.').next().unwrap_or(base);
+                    *self.enums.get(key).ok_or_else(|| format!("enumeratedValues {base} not found"))?
+                }
+                None => enums,
+            };
+            for value in children(enums, "enumeratedValue") {
+                let (name, v) = match (text(value, "name"), text(value, "value")) {
+                    (Some(n), Some(v)) => (n, v),
+                    _ => continue, // isDefault entries have no value
+                };
+                let v = match parse_svd_number(v) {
+                    Ok(v) => v,
+                    Err(_) => continue, // values with don't care bits can't be written
+                };
+                if names.contains(&name) {
+                    continue;
+                }
+                names.push(name);
+                entries.push(format!("{name}={v}"));
+            }
+        }
+        Ok(entries.join(", "))
+    }
+}
+
+///
+/// lsb and msb of a field from bitRange, lsb/msb or bitOffset/bitWidth
+///
+fn field_bits(element: &Element) -> Result<(u64, u64), String> {
+    if let Some(range) = element.text("bitRange") {
+        let inner = range.trim_start_matches('[').trim_end_matches(']');
+        let parts: Vec<&str> = inner.split(':').collect();
+        if parts.len() != 2 {
+            return Err(format!("invalid bitRange {range}"));
+        }
+        return Ok((parse_value(parts[1])?, parse_value(parts[0])?));
+    }
+    if let (Some(lsb), Some(msb)) = (element.number("lsb")?, element.number("msb")?) {
+        return Ok((lsb, msb));
+    }
+    match element.number("bitOffset")? {
+        Some(offset) => {
+            let width = element.number("bitWidth")?.unwrap_or(1);
+            if width == 0 {
+                return Err("bitWidth of 0".to_string());
+            }
+            Ok((offset, offset + width - 1))
+        }
+        None => Err("no bitRange, lsb/msb or bitOffset".to_string()),
+    }
+}
+
+///
+/// Find an element by a dotted path of names from the device, e.g. "USART1.CR1"
+///
+fn find_path<'a, 'i>(device: Node<'a, 'i>, path: &str) -> Option<Node<'a, 'i>> {
+    let mut current = child(device, "peripherals")?;
+    for name in path.split('.') {
+        current = current.descendants().skip(1)
+            .filter(|n| n.is_element() && matches!(n.tag_name().name(), "peripheral" | "cluster" | "register" | "field"))
+            .find(|n| text(*n, "name") == Some(name))?;
+    }
+    Some(current)
+}
+
+///
+/// Convert an SVD document into a register definition document
+///
+pub fn svd_to_document(content: &str) -> Result<Yaml, String> {
+    let xml = Document::parse(content.trim_start_matches('\u{feff}')).map_err(|e| format!("invalid SVD: {e}"))?;
+    let device = xml.root_element();
+    if device.tag_name().name() != "device" {
+        return Err(format!("invalid SVD: root element is <{}>, not <device>", device.tag_name().name()));
+    }
+    let peripherals = child(device, "peripherals").ok_or("invalid SVD: no <peripherals>")?;
+
+    let importer = Importer {
+        enums: device.descendants()
+            .filter(|n| n.is_element() && n.tag_name().name() == "enumeratedValues")
+            .filter_map(|n| text(n, "name").map(|name| (name.to_string(), n)))
+            .collect(),
+    };
+
+    let device_element = Element { chain: vec![device] };
+    let device_defaults = importer.defaults(&device_element, &Defaults::default())?;
+    let siblings: Vec<Node> = children(peripherals, "peripheral").collect();
+
+    // base addresses first, each block is mapped from its own
+    let mut blocks: Vec<(Element, String, u64)> = Vec::new();
+    for node in &siblings {
+        let element = importer.element(*node, &siblings, device)?;
+        let name = element.text("name").ok_or("peripheral without a name")?;
+        let base = text(*node, "baseAddress").map(parse_svd_number).transpose()
+            .map_err(|e| format!("invalid baseAddress for {name}: {e}"))?
+            .ok_or_else(|| format!("no baseAddress for {name}"))?;
+        let (_, _, names) = dim_instances(&element, name)?;
+        let increment = element.number("dimIncrement")?.unwrap_or(0);
+        for (i, instance_name) in names.into_iter().enumerate() {
+            blocks.push((importer.element(*node, &siblings, device)?, instance_name, base + i as u64 * increment));
+        }
+    }
+
+    let mut registers = Hash::new();
+    for (element, name, base) in &blocks {
+        let defaults = importer.defaults(element, &device_defaults)?;
+        let source = element.chain.iter().find(|n| child(**n, "registers").is_some())
+            .and_then(|n| child(*n, "registers"));
+        let mut block = Hash::new();
+        if let Some(d) = description(element) {
+            block.insert(Yaml::String("description".into()), Yaml::String(d));
+        }
+        let mut length = match source {
+            Some(regs) => importer.registers(&mut block, regs, name, 0, &defaults, device)?,
+            None => 0,
+        };
+        for address_block in element.chain.iter().flat_map(|n| children(*n, "addressBlock")) {
+            let offset = text(address_block, "offset").map(parse_svd_number).transpose()?.unwrap_or(0);
+            let size = text(address_block, "size").map(parse_svd_number).transpose()?.unwrap_or(0);
+            length = length.max(offset + size);
+        }
+        block.insert(Yaml::String("base".into()), yaml_number(*base));
+        block.insert(Yaml::String("length".into()), yaml_number(length));
+        registers.insert(Yaml::String(name.clone()), Yaml::Hash(block));
+    }
+
+    let mut metadata = Hash::new();
+    metadata.insert(Yaml::String("root".into()), Yaml::String("registers".into()));
+    metadata.insert(Yaml::String("terminal-fields".into()),
+                    Yaml::Array(vec![Yaml::String("offset".into()), Yaml::String("parent".into())]));
+
+    let mut doc = Hash::new();
+    doc.insert(Yaml::String("completion-metadata".into()), Yaml::Hash(metadata));
+    if let Some(name) = text(device, "name") {
+        doc.insert(Yaml::String("description".into()), Yaml::String(name.to_string()));
+    }
+    doc.insert(Yaml::String("device".into()), Yaml::String("/dev/mem".into()));
+    doc.insert(Yaml::String("registers".into()), Yaml::Hash(registers));
+    Ok(Yaml::Hash(doc))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+///
+/// The SVD access, modifiedWriteValues and readAction of an access type, the reverse of read_write()
+///
+fn svd_access(access: RegisterAccess) -> (&'static str, Option<&'static str>, Option<&'static str>) {
+    match access {
+        RegisterAccess::ReadOnly => ("read-only", None, None),
+        RegisterAccess::WriteOnly => ("write-only", None, None),
+        RegisterAccess::ReadWrite | RegisterAccess::Unspecified => ("read-write", None, None),
+        RegisterAccess::Write1Clear => ("read-write", Some("oneToClear"), None),
+        RegisterAccess::Write1Set => ("read-write", Some("oneToSet"), None),
+        RegisterAccess::ReadClear => ("read-only", None, Some("clear")),
+        RegisterAccess::WriteClear => ("write-only", Some("clear"), None),
+        RegisterAccess::WriteSet => ("write-only", Some("set"), None),
+    }
+}
+
+struct SvdWriter {
+    out: String,
+    indent: usize,
+}
+
+impl SvdWriter {
+    fn open(&mut self, tag: &str) {
+        self.out.push_str(&format!("{:1$}<{tag}>\n", "", self.indent));
+        self.indent += 2;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.indent -= 2;
+        self.out.push_str(&format!("{:1$}</{tag}>\n", "", self.indent));
+    }
+
+    fn element(&mut self, tag: &str, value: &str) {
+        self.out.push_str(&format!("{:1$}<{tag}>{2}</{tag}>\n", "", self.indent, escape(value)));
+    }
+
+    ///
+    /// The access of a register or field and its write and read actions, with a
+    /// register's resetValue between them where the schema puts it
+    ///
+    fn access(&mut self, access: RegisterAccess, reset: Option<u64>) {
+        let (access, modified, read_action) = svd_access(access);
+        self.element("access", access);
+        if let Some(reset) = reset {
+            self.element("resetValue", &format!("0x{reset:X}"));
+        }
+        if let Some(m) = modified {
+            self.element("modifiedWriteValues", m);
+        }
+        if let Some(r) = read_action {
+            self.element("readAction", r);
+        }
+    }
+
+    fn field(&mut self, name: &str, def: &RegisterDef) {
+        let (hi, lo) = def.op.bits();
+        self.open("field");
+        self.element("name", name);
+        if let Some(d) = &def.description {
+            self.element("description", d);
+        }
+        self.element("bitRange", &format!("[{hi}:{lo}]"));
+        self.access(def.op.access_type(), None);
+        if !def.op.values.is_empty() {
+            self.open("enumeratedValues");
+            for (label, value) in &def.op.values {
+                self.open("enumeratedValue");
+                self.element("name", &identifier(label));
+                self.element("value", &format!("{value}"));
+                self.close("enumeratedValue");
+            }
+            self.close("enumeratedValues");
+        }
+        self.close("field");
+    }
+}
+
+///
+/// Write a register map as an SVD document
+///
+/// Each block becomes a peripheral whose base is its lowest register, words
+/// become registers named by their path within the block and the registers naming a
+/// word as their parent become its fields. A word that does not span its whole width
+/// or has symbolic values is given a single field of its own.
+///
+pub fn export_svd(map: &RegisterMap, name: &str) -> String {
+    let mut w = SvdWriter { out: String::new(), indent: 0 };
+    w.out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    w.out.push_str("<device schemaVersion=\"1.3\" xmlns:xs=\"http://www.w3.org/2001/XMLSchema-instance\" xs:noNamespaceSchemaLocation=\"CMSIS-SVD.xsd\">\n");
+    w.indent = 2;
+    w.element("name", &identifier(name));
+    w.element("version", "1.0");
+    w.element("description", if map.description.is_empty() { name } else { &map.description });
+    w.element("addressUnitBits", "8");
+    w.element("width", "32");
+    w.element("size", "32");
+    w.element("access", "read-write");
+    w.element("resetValue", "0x00000000");
+    w.element("resetMask", "0xFFFFFFFF");
+    w.open("peripherals");
+
+    for block in map.blocks() {
+        let words: Vec<&RegisterDef> = map.words().filter(|r| map.block(r.path()) == block).collect();
+        if words.is_empty() {
+            continue;
+        }
+        let (start, end) = map.block_span(block);
+
+        w.open("peripheral");
+        w.element("name", &identifier(block));
+        w.element("baseAddress", &format!("0x{:08X}", map.region(block).base + start));
+        w.open("addressBlock");
+        w.element("offset", "0x0");
+        w.element("size", &format!("0x{:X}", end - start));
+        w.element("usage", "registers");
+        w.close("addressBlock");
+        w.open("registers");
+
+        for word in words {
+            let path = word.path();
+            let relative = path.strip_prefix(block).unwrap_or(path).trim_start_matches('.');
+            let reg_name = identifier(if relative.is_empty() { path } else { relative });
+            let width = word.op.width.bits();
+            let whole = if width == 64 { u64::MAX } else { (1u64 << width) - 1 };
+
+            w.open("register");
+            w.element("name", &reg_name);
+            if let Some(d) = &word.description {
+                w.element("description", d);
+            }
+            w.element("addressOffset", &format!("0x{:X}", word.op.offset - start));
+            w.element("size", &format!("{width}"));
+            w.access(word.op.access_type(), word.reset);
+
+            let fields: Vec<&RegisterDef> = map.fields_of(path).collect();
+            let own_field = word.op.read_mask != whole || !word.op.values.is_empty();
+            if !fields.is_empty() || own_field {
+                w.open("fields");
+                if own_field {
+                    w.field(&reg_name, word);
+                }
+                for field in fields {
+                    w.field(&identifier(field.field_name()), field);
+                }
+                w.close("fields");
+            }
+            w.close("register");
+        }
+        w.close("registers");
+        w.close("peripheral");
+    }
+    w.close("peripherals");
+    w.out.push_str("</device>\n");
+    w.out
+}
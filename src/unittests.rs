@@ -24,27 +24,67 @@
 
 #[cfg(test)]
 pub mod rtool_tests {
-    use std::io::Write;
-    use aep_rust_common::descender::{Descender as OtherDescender, Descender};
     use aep_rust_common::yaml_descender::YamlDescender;
-    use crate::register_op::parse_bits;
-    use crate::register_tool::RegisterTool;
+    use crate::register_op::{parse_bits, parse_values, AccessOptions};
+    use crate::register_tool::{Access, RegisterTool};
+    use crate::output::{signed, OutputFormat};
+    use crate::decode::{decode, decode_table};
+    use crate::snapshot::{config_hash, SavedRegister, Snapshot};
+    use crate::value::parse_value;
+    use crate::unsafes::{page_size, page_span, Mapping};
+    use crate::config::{descender_from_str, detect_format, load_config, load_descender, load_document, ConfigFormat};
+    use crate::compose::{composed_files, load_composed};
+    use crate::register_map::{parent_chain, parent_in, word_of, Region, RegisterMap};
+    use crate::svd::{export_svd, is_svd, svd_to_document};
+    use crate::codegen::{c_header, rust_module};
+    use crate::doc;
+    use crate::validate::validate;
+    use crate::address_space::{allowed_aliases, AddressSpace, Severity};
+    use crate::pac::{Bits, Block, FieldValue, Registers};
+    use crate::register_op::AccessWidth;
 
 
     #[test]
     fn test_bitmask() {
-        let (mask, lo) = parse_bits("4:2").unwrap();
+        let (mask, lo) = parse_bits("4:2", 32).unwrap();
         assert_eq!(mask, 0b011100);
         assert_eq!(lo, 2);
         
-        let (mask, lo) = parse_bits("3:3").unwrap() ;
+        let (mask, lo) = parse_bits("3:3", 32).unwrap() ;
         assert_eq!(mask, 0b01000) ;
         assert_eq!(lo, 3);
 
-        let (mask, lo) = parse_bits("31:0").unwrap() ;
+        let (mask, lo) = parse_bits("31:0", 32).unwrap() ;
         assert_eq!(mask, 0xFFFFFFFF) ;
         assert_eq!(lo, 0);
+
+        let (mask, lo) = parse_bits("63:32", 64).unwrap() ;
+        assert_eq!(mask, 0xFFFFFFFF_00000000) ;
+        assert_eq!(lo, 32);
+
+        let (mask, lo) = parse_bits("63:0", 64).unwrap() ;
+        assert_eq!(mask, u64::MAX) ;
+        assert_eq!(lo, 0);
+
+        assert!(parse_bits("32:0", 32).is_err());
+        assert!(parse_bits("8:0", 8).is_err());
+    }
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("42").unwrap(), 42);
+        assert_eq!(parse_value("0x7E20_0000").unwrap(), 0x7E200000);
+        assert_eq!(parse_value("0X1f").unwrap(), 0x1F);
+        assert_eq!(parse_value("0o17").unwrap(), 0o17);
+        assert_eq!(parse_value("0b0010_0001").unwrap(), 0b00100001);
+        assert_eq!(parse_value("1_000").unwrap(), 1000);
+
+        assert_eq!(parse_value("0b012").unwrap_err(), "invalid binary digit '2' at position 5 in \"0b012\"");
+        assert_eq!(parse_value("0x1g").unwrap_err(), "invalid hex digit 'g' at position 4 in \"0x1g\"");
+        assert!(parse_value("0x").is_err());
+        assert!(parse_value("").is_err());
+        assert!(parse_value("0x1_0000_0000_0000_0000").is_err());
     }
+
     #[test]
     fn test_gather_and_apply_regsters() {
         let regspecs = vec!["GPIO.words.function2=0", "GPIO.pins[27].function=1", "GPIO.words.function2", "GPIO.pins[27].function"] ;
@@ -52,13 +92,13 @@ pub mod rtool_tests {
         let working_dir = env!("CARGO_MANIFEST_DIR");
         let config_file = format!("{}/register_tool.yaml", working_dir);
 
-        let descender = Box::new(YamlDescender::new_from_file(&*config_file, true).unwrap()) as Box<dyn Descender<dyn Write>> ;
-        let mut register_tool = RegisterTool::new(descender).unwrap() ;
+        let descender = load_descender(&config_file).unwrap() ;
+        let mut register_tool = RegisterTool::new(descender) ;
 
         register_tool.gather_regs(&regspecs).expect("TODO: panic message");
         register_tool.set_test_area() ;
 
-        let replies = register_tool.apply_registers(|v| {
+        let replies = register_tool.apply_registers(|_reg, v| {
             Ok(v)
         }).expect("TODO: panic message");
 
@@ -69,22 +109,1014 @@ pub mod rtool_tests {
         assert_eq!(replies[3].clone().unwrap(), 0x00000001);
     }
 
+    #[test]
+    fn test_symbolic_values() {
+        let values = parse_values("input=0b000, output=0b001, alt0=4").unwrap();
+        assert_eq!(values, vec![("input".to_string(), 0), ("output".to_string(), 1), ("alt0".to_string(), 4)]);
+        assert!(parse_values("input=0b000, input=0b001").is_err());
+        assert!(parse_values("input").is_err());
+
+        let working_dir = env!("CARGO_MANIFEST_DIR");
+        let config_file = format!("{}/register_tool.yaml", working_dir);
+        let descender = load_descender(&config_file).unwrap() ;
+        let mut register_tool = RegisterTool::new(descender) ;
+
+        register_tool.gather_regs(&vec!["GPIO.pins[27].function=output", "GPIO.pins[27].function"]).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|reg, v| {
+            assert_eq!(reg.label(v), Some("output"));
+            Ok(v)
+        }).unwrap();
+        assert_eq!(replies[1].clone().unwrap(), 1);
+
+        let errs = register_tool.gather_regs(&vec!["GPIO.pins[27].function=outptu"]).unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(errs[0].contains("expected a number or one of: input, output"));
+    }
+
+    #[test]
+    fn test_register_widths() {
+        let config = r#"{device: /dev/mem, base: 0x40000000, length: 0x100,
+            completion-metadata: {root: registers},
+            registers: {UART: {
+                data: {offset: 0x10, width: 8, read-write: rw},
+                status: {offset: 0x11, width: 8, read-write: rw},
+                divisor: {offset: 0x12, width: 16, read-write: rw},
+                counter: {offset: 0x18, width: 64, read-write: rw},
+                counter_hi: {parent: UART.counter, bits: "63:32"}}}}"#;
+        let descender = YamlDescender::new(config, true).unwrap() ;
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+
+        let regspecs = vec!["UART.data=0xAB", "UART.divisor=0x1234", "UART.counter_hi=0x8000_0001",
+                            "UART.status", "UART.data", "UART.divisor", "UART.counter"] ;
+        register_tool.gather_regs(&regspecs).unwrap();
+        register_tool.set_test_area() ;
+
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies[3].clone().unwrap(), 0);
+        assert_eq!(replies[4].clone().unwrap(), 0xAB);
+        assert_eq!(replies[5].clone().unwrap(), 0x1234);
+        assert_eq!(replies[6].clone().unwrap(), 0x80000001_00000000);
+    }
+
+    #[test]
+    fn test_mmio_checks() {
+        let config = r#"{device: /dev/mem, base: 0x40000000, length: 0x100,
+            completion-metadata: {root: registers},
+            registers: {misaligned: {offset: 0x12, width: 32, read-write: rw},
+                        beyond: {offset: 0x100, width: 32, read-write: rw}}}"#;
+        let descender = YamlDescender::new(config, true).unwrap() ;
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+
+        let errs = register_tool.gather_regs(&vec!["misaligned"]).unwrap_err();
+        assert!(errs[0].contains("not aligned"));
+
+        let mut register_tool = RegisterTool::new(Box::new(YamlDescender::new(config, true).unwrap())) ;
+        let errs = register_tool.gather_regs(&vec!["beyond"]).unwrap_err();
+        assert_eq!(errs[0], "Register beyond at offset 0x100 + 4 bytes is beyond the mapped length 0x100");
+
+        let mapping = Mapping::buffer(0x100);
+        assert!(mapping.read(0x100, AccessWidth::Bits32).unwrap_err().contains("beyond mapped length"));
+        assert!(mapping.write(0xFE, AccessWidth::Bits32, 1).unwrap_err().contains("beyond mapped length"));
+        assert!(mapping.read(0x2, AccessWidth::Bits32).unwrap_err().contains("4-byte aligned"));
+    }
+
+    #[test]
+    fn test_mapping() {
+        let mapping = Mapping::buffer(0x10);
+        assert_eq!(mapping.len(), 0x10);
+        mapping.write(0x8, AccessWidth::Bits64, 0x1122334455667788).unwrap();
+        assert_eq!(mapping.read(0xC, AccessWidth::Bits32).unwrap(), 0x11223344);
+        assert_eq!(mapping.read(0x9, AccessWidth::Bits8).unwrap(), 0x77);
+
+        let config = r#"{device: /dev/mem, base: 0x40000000, length: 0x100,
+            completion-metadata: {root: registers},
+            registers: {status: {offset: 0x0, width: 32, read-write: ro}}}"#;
+        let descender = YamlDescender::new(config, true).unwrap() ;
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+        register_tool.gather_regs(&vec!["status"]).unwrap();
+        let err = register_tool.apply_registers(|_, v| Ok(v)).unwrap_err();
+        assert_eq!(err, "Register memory is not mapped");
+    }
+
+    #[test]
+    fn test_device_errors() {
+        let err = Mapping::device("/no/such/device", 0x1000, 0x100).err().unwrap();
+        assert!(err.starts_with("Failed to open /no/such/device: No such file or directory (errno: 2)"), "{err}");
+        assert!(err.ends_with("check the 'device' of the register file"));
+        let err = Mapping::device("/dev/mem\0", 0x1000, 0x100).err().unwrap();
+        assert!(err.contains("contains a NUL byte"));
+
+        // /dev/zero maps like a device without needing one
+        let mapping = Mapping::device("/dev/zero", 0x10, 0x20).unwrap();
+        assert_eq!(mapping.read(0x8, AccessWidth::Bits64).unwrap(), 0);
+        mapping.write(0x8, AccessWidth::Bits32, 0x5A).unwrap();
+        assert_eq!(mapping.read(0x8, AccessWidth::Bits32).unwrap(), 0x5A);
+    }
+
+    #[test]
+    fn test_page_span() {
+        assert_eq!(page_span(0x7E200000, 0x2000, 0x1000), (0x7E200000, 0x2000, 0));
+        assert_eq!(page_span(0x7E215040, 0x40, 0x1000), (0x7E215000, 0x1000, 0x40));
+        assert_eq!(page_span(0x7E215FF0, 0x20, 0x1000), (0x7E215000, 0x2000, 0xFF0));
+        assert_eq!(page_span(0x40001234, 0x10, 0x10000), (0x40000000, 0x10000, 0x1234));
+    }
+
+    #[test]
+    fn test_gather_bounds() {
+        let config = r#"{device: /dev/mem, base: 0x40000000, length: 0x100,
+            completion-metadata: {root: registers},
+            registers: {last: {offset: 0xF8, width: 64, read-write: rw},
+                        wide: {offset: 0xF8, width: 32, read-write: rw, bits: "7:0"},
+                        level: {offset: 0x100, width: 32, read-write: ro},
+                        set: {offset: 0x10, width: 32, read-write: wo, shadow: level},
+                        set_bit: {parent: set, bits: "3:3"}}}"#;
+        let mut register_tool = RegisterTool::new(Box::new(YamlDescender::new(config, true).unwrap())) ;
+        register_tool.gather_regs(&vec!["last", "wide=1"]).unwrap();
+
+        let errs = register_tool.gather_regs(&vec!["set=1", "set_bit", "level"]).unwrap_err();
+        assert_eq!(errs, vec!["Shadow of register set at offset 0x100 + 4 bytes is beyond the mapped length 0x100",
+                              "Shadow of register set_bit at offset 0x100 + 4 bytes is beyond the mapped length 0x100",
+                              "Register level at offset 0x100 + 4 bytes is beyond the mapped length 0x100"]);
+    }
+
+    #[test]
+    fn test_write_strategies() {
+        let config = r#"{device: /dev/mem, base: 0x40000000, length: 0x100,
+            completion-metadata: {root: registers},
+            registers: {
+                raw: {offset: 0x40, read-write: rw},
+                status: {offset: 0x40, read-write: w1c},
+                flag3: {parent: status, bits: "3:3"},
+                enable: {offset: 0x44, read-write: w1s},
+                enable1: {parent: enable, bits: "1:1"},
+                events: {offset: 0x48, read-write: rc}}}"#;
+        let descender = YamlDescender::new(config, true).unwrap() ;
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+
+        let regspecs = vec!["raw=0xFF", "flag3=1", "raw", "enable1=1", "enable"] ;
+        register_tool.gather_regs(&regspecs).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies[2].clone().unwrap(), 0x08);
+        assert_eq!(replies[4].clone().unwrap(), 0x02);
+
+        let errs = register_tool.gather_regs(&vec!["events"]).unwrap_err();
+        assert!(errs[0].contains("cleared by reading"));
+
+        register_tool.set_options(AccessOptions { force: true, ..Default::default() });
+        register_tool.gather_regs(&vec!["events"]).unwrap();
+    }
+
+    #[test]
+    fn test_value_range() {
+        let working_dir = env!("CARGO_MANIFEST_DIR");
+        let config_file = format!("{}/register_tool.yaml", working_dir);
+        let descender = load_descender(&config_file).unwrap() ;
+        let mut register_tool = RegisterTool::new(descender) ;
+
+        register_tool.gather_regs(&vec!["GPIO.pins[0].function=7"]).unwrap();
+
+        let errs = register_tool.gather_regs(&vec!["GPIO.pins[0].function=8", "GPIO.pins[27].function=0x100000"]).unwrap_err();
+        assert_eq!(errs.len(), 2);
+        assert!(errs[0].contains("must be 0 to 7 (0x7)"));
+
+        register_tool.set_options(AccessOptions { truncate: true, ..Default::default() });
+        register_tool.gather_regs(&vec!["GPIO.pins[27].function=0xF", "GPIO.words.function2"]).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies.last().unwrap().clone().unwrap(), 0x7 << 21);
+    }
+
+    #[test]
+    fn test_json_config() {
+        let config = r#"
+        {
+            "completion-metadata": {"root": "registers", "terminal-fields": ["offset", "parent"]},
+            "device": "/dev/mem", "base": 1073741824, "length": 256,
+            "registers": {
+                "GPIO": {
+                    "pins": [
+                        {"function": {"bits": "2:0", "parent": "GPIO.words.function0", "values": "input=0, output=1"},
+                         "set": {"bits": "0:0", "parent": "GPIO.words.set0", "shadow": "GPIO.words.level0"}}
+                    ],
+                    "words": {
+                        "function0": {"offset": 0, "read-write": "rw", "width": 32},
+                        "set0": {"offset": 28, "read-write": "wo", "width": 32},
+                        "level0": {"offset": 52, "read-write": "ro", "width": 32}
+                    }
+                }
+            }
+        }"#;
+        assert_eq!(detect_format(config), ConfigFormat::Json);
+        assert_eq!(detect_format(r"{device: /dev/mem, base: 0x40000000, length: 0x1000}"), ConfigFormat::Yaml);
+
+        let mut register_tool = RegisterTool::new(descender_from_str(config).unwrap()) ;
+        register_tool.gather_regs(&vec!["GPIO.pins[0].function=output", "GPIO.pins[0].set=1", "GPIO.pins[0].set", "GPIO.words.function0"]).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies[2].clone().unwrap(), 0);
+        assert_eq!(replies[3].clone().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_svd_import() {
+        let svd = r#"<?xml version="1.0" encoding="utf-8"?>
+        <device schemaVersion="1.3">
+          <name>TESTCHIP</name>
+          <size>32</size><access>read-write</access><resetValue>0</resetValue>
+          <peripherals>
+            <peripheral>
+              <name>USART1</name><baseAddress>0x40011000</baseAddress>
+              <addressBlock><offset>0</offset><size>0x400</size><usage>registers</usage></addressBlock>
+              <registers>
+                <register>
+                  <name>SR</name><addressOffset>0x00</addressOffset><resetValue>0x00C0</resetValue>
+                  <fields>
+                    <field><name>RXNE</name><bitOffset>5</bitOffset><bitWidth>1</bitWidth>
+                      <modifiedWriteValues>oneToClear</modifiedWriteValues></field>
+                  </fields>
+                </register>
+                <register>
+                  <name>CR1</name><addressOffset>0x0C</addressOffset>
+                  <fields>
+                    <field><name>UE</name><bitRange>[13:13]</bitRange>
+                      <enumeratedValues><name>UE_enum</name>
+                        <enumeratedValue><name>disabled</name><value>0</value></enumeratedValue>
+                        <enumeratedValue><name>enabled</name><value>#1</value></enumeratedValue>
+                      </enumeratedValues>
+                    </field>
+                    <field><name>M</name><lsb>12</lsb><msb>12</msb><enumeratedValues derivedFrom="UE_enum"/></field>
+                  </fields>
+                </register>
+                <register>
+                  <dim>4</dim><dimIncrement>4</dimIncrement>
+                  <name>GTPR[%s]</name><addressOffset>0x20</addressOffset><size>16</size>
+                </register>
+                <register>
+                  <dim>2</dim><dimIncrement>4</dimIncrement><dimIndex>A,B</dimIndex>
+                  <name>PORT%s</name><addressOffset>0x30</addressOffset><access>read-only</access>
+                </register>
+                <register>
+                  <name>STAMP</name><addressOffset>0x38</addressOffset><size>64</size><resetValue>0xFFFFFFFFFFFFFFFF</resetValue>
+                </register>
+              </registers>
+            </peripheral>
+            <peripheral derivedFrom="USART1">
+              <name>USART2</name><baseAddress>0x40004400</baseAddress>
+            </peripheral>
+          </peripherals>
+        </device>"#;
+        assert_eq!(detect_format(svd), ConfigFormat::Svd);
+
+        let mut register_tool = RegisterTool::new(descender_from_str(svd).unwrap()) ;
+        let regspecs = vec!["USART1.CR1.UE=enabled", "USART2.CR1.M=enabled", "USART1.GTPR[2]=0xFFFF", "USART1.SR.RXNE=1",
+                            "USART1.CR1", "USART2.CR1", "USART1.GTPR[2]", "USART1.SR", "USART1.PORTB"] ;
+        register_tool.gather_regs(&regspecs).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies[4].clone().unwrap(), 1 << 13);
+        assert_eq!(replies[5].clone().unwrap(), 1 << 12);
+        assert_eq!(replies[6].clone().unwrap(), 0xFFFF);
+        assert_eq!(replies[7].clone().unwrap(), 1 << 5);
+
+        let errs = register_tool.gather_regs(&vec!["USART1.PORTA=1", "USART1.GTPR[4]"]).unwrap_err();
+        assert_eq!(errs.len(), 2);
+
+        // each peripheral is mapped from its own base, the file gives none to span them all
+        let doc = svd_to_document(svd).unwrap();
+        assert!(doc["base"].is_badvalue() && doc["length"].is_badvalue());
+        assert_eq!(doc["registers"]["USART2"]["base"].as_i64(), Some(0x40004400));
+        assert_eq!(doc["registers"]["USART1"]["STAMP"]["reset"].as_str(), Some("0xFFFFFFFFFFFFFFFF"));
+        let mut descender = descender_from_str(svd).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(map.get("USART1.STAMP").unwrap().reset, Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_svd_export() {
+        let config = r#"
+completion-metadata: {root: registers}
+description: "Test <chip> & co"
+device: /dev/mem
+base: 0x40000000
+length: 0x2000
+registers:
+  UART:
+    CTRL: {offset: 0x10, reset: 0x3, description: "Control"}
+    mode: {parent: UART.CTRL, bits: "2:1", values: "off=0, tx=1, rx=2, both=3"}
+    enable: {parent: UART.CTRL, bits: "0:0"}
+    STATUS: {offset: 0x14, read-write: w1c, width: 16, bits: "7:0", reset: 0x0}
+  TIMER:
+    COUNT: {offset: 0x1000, read-write: ro}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(map.words().count(), 3);
+        assert_eq!(map.fields_of("UART.CTRL").count(), 2);
+        assert_eq!(map.get("UART.mode").unwrap().reset, Some(1));
+
+        let svd = export_svd(&map, "test-chip");
+        assert!(is_svd(&svd));
+        assert!(svd.contains("<description>Test &lt;chip&gt; &amp; co</description>"));
+        assert!(svd.contains("<modifiedWriteValues>oneToClear</modifiedWriteValues>"));
+
+        // registers and fields give their elements in the order the schema requires
+        let register_order = ["name", "displayName", "description", "alternateGroup", "alternateRegister", "addressOffset",
+                              "size", "access", "protection", "resetValue", "resetMask", "dataType",
+                              "modifiedWriteValues", "writeConstraint", "readAction", "fields"];
+        let field_order = ["name", "description", "bitOffset", "bitWidth", "lsb", "msb", "bitRange", "access",
+                           "modifiedWriteValues", "writeConstraint", "readAction", "enumeratedValues"];
+        let xml = roxmltree::Document::parse(&svd).unwrap();
+        let mut checked = 0;
+        for node in xml.descendants().filter(|n| n.has_tag_name("register") || n.has_tag_name("field")) {
+            let order: &[&str] = if node.has_tag_name("register") { &register_order } else { &field_order };
+            let positions: Vec<usize> = node.children().filter(|c| c.is_element())
+                .map(|c| order.iter().position(|o| c.has_tag_name(*o)).unwrap())
+                .collect();
+            assert!(positions.is_sorted(), "{:?} out of order", node.children().filter(|c| c.is_element()).map(|c| c.tag_name().name()).collect::<Vec<_>>());
+            checked += 1;
+        }
+        assert_eq!(checked, 6);
+
+        let mut register_tool = RegisterTool::new(descender_from_str(&svd).unwrap()) ;
+        let regspecs = vec!["UART.CTRL.mode=rx", "UART.CTRL.enable=1", "UART.STATUS.STATUS=0x5A",
+                            "UART.CTRL", "UART.STATUS", "TIMER.COUNT"] ;
+        register_tool.gather_regs(&regspecs).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        assert_eq!(replies[3].clone().unwrap(), 0b101);
+        assert_eq!(replies[4].clone().unwrap(), 0x5A);
+        assert_eq!(replies[5].clone().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_c_header() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x40000000
+length: 0x2000
+registers:
+  UART:
+    CTRL: {offset: 0x10, reset: 0x3}
+    mode: {parent: UART.CTRL, bits: "2:1", values: "off=0, tx=1, rx=2, reset=3"}
+    DATA: {offset: 0x18, width: 8}
+  TIMER:
+    COUNT: {offset: 0x1000, width: 64}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        let header = c_header(&map, "test-chip", true).unwrap();
+        for line in ["#ifndef TEST_CHIP_REGISTERS_H", "#define TEST_CHIP_BASE 0x40000000u",
+                     "#define UART_MODE_OFFSET 0x10u", "#define UART_MODE_SHIFT 1u", "#define UART_MODE_MASK 0x6u",
+                     "#define UART_MODE_RESET 0x1u", "#define UART_MODE_VAL_RX 0x2u", "#define UART_MODE_VAL_RESET 0x3u", "#define TIMER_COUNT_SIZE 64u",
+                     "#define TIMER_COUNT_MASK 0xFFFFFFFFFFFFFFFFull", "#define UART_BLOCK_OFFSET 0x10u",
+                     "    uint8_t reserved_0x4[4];\n    volatile uint8_t data;", "} uart_regs_t;"] {
+            assert!(header.contains(line), "missing {line}");
+        }
+
+        // no base of the file's own, and a register overlapping and going past the one before
+        let overlap = r#"
+completion-metadata: {root: registers}
+registers:
+  UART:
+    LO: {offset: 0x10}
+    BOTH: {offset: 0x10, width: 64}
+    DATA: {offset: 0x18}
+"#;
+        let doc = load_document(overlap).unwrap();
+        let mut descender = descender_from_str(overlap).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        let header = c_header(&map, "overlap", true).unwrap();
+        assert!(!header.contains("OVERLAP_BASE") && !header.contains("OVERLAP_LENGTH"));
+        assert!(header.contains("    /* both at 0x0 overlaps the register before it */\n    uint8_t reserved_0x4[4];\n    volatile uint32_t data;"));
+        assert!(header.contains("_Static_assert(sizeof(uart_regs_t) == 0xC"));
+
+        let clash = r#"
+completion-metadata: {root: registers}
+registers:
+  UART:
+    CTRL: {offset: 0x10}
+    mode: {parent: UART.CTRL, bits: "0:0", values: "offset=0"}
+    mode_val: {parent: UART.CTRL, bits: "1:1"}
+"#;
+        let doc = load_document(clash).unwrap();
+        let mut descender = descender_from_str(clash).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(c_header(&map, "clash", false).unwrap_err(), vec!["UART.mode_val: identifier UART_MODE_VAL_OFFSET is already defined"]);
+    }
+
+    // the module rust_module gives for tools/data/test_chip.yaml, compiled here
+    include!("../tools/data/test_chip.rs");
+
+    #[test]
+    fn test_rust_module() {
+        let (doc, mut descender) = load_config("tools/data/test_chip.yaml").unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        let source = rust_module(&map, "test_chip", "crate").unwrap();
+        assert_eq!(source, include_str!("../tools/data/test_chip.rs"), "tools/data/test_chip.rs is out of date");
+        for line in ["pub mod test_chip {", "use crate::pac::{Bits, Block, FieldValue, ReadClearable, Readable, Register, Writable};",
+                     "const MASK: u64 = 0x6;", "type Value = UART_MODE_VALUE;", "        type_,\n",
+                     "UART_MODE_VALUE::rx => 0x2,", "impl Readable for UART_STATUS {}", "impl Readable for UART_SET {}",
+                     "impl Block for TIMER_BLOCK {\n        const DEVICE: &'static str = \"/dev/mem\";\n        const BASE: u64 = 0x40010000;",
+                     "impl Register for TIMER_COUNT {\n        type Block = TIMER_BLOCK;"] {
+            assert!(source.contains(line), "missing {line}");
+        }
+        assert!(!source.contains("impl Writable for UART_STATUS"));
+        // an rc register is not read by accident, only by read_clearing, unless it has a shadow
+        assert!(!source.contains("impl Readable for UART_EVENTS") && source.contains("impl ReadClearable for UART_EVENTS {}"));
+        assert!(source.contains("impl Readable for TIMER_COUNT {}") && !source.contains("impl ReadClearable for TIMER_COUNT"));
+
+        use test_chip::*;
+        let regs = Registers::<UART_BLOCK>::new(Mapping::buffer(UART_BLOCK::LENGTH));
+        regs.write::<UART_MODE>(UART_MODE_VALUE::type_).unwrap();
+        assert_eq!(regs.read::<UART_MODE>().unwrap(), UART_MODE_VALUE::type_);
+        assert_eq!(regs.mapping().read(0x10, AccessWidth::Bits32).unwrap(), 0x6);
+        regs.mapping().write(0x18, AccessWidth::Bits32, 0x5).unwrap();
+        assert_eq!(regs.read::<UART_SET>().unwrap(), Bits::new::<5>());
+        regs.write::<UART_STAMP>(Bits::new::<{ u64::MAX }>()).unwrap();
+        assert_eq!(regs.read::<UART_STAMP>().unwrap().get(), u64::MAX);
+        regs.mapping().write(0x30, AccessWidth::Bits32, 0x3).unwrap();
+        assert_eq!(regs.read_clearing::<UART_EVENTS>().unwrap(), Bits::new::<3>());
+        assert!(UART_MODE_VALUE::from_bits(4).is_err());
+        assert!(Bits::<2>::from_bits(4).is_err());
+
+        // TIMER's offsets are from its own base, in a mapping of its own
+        let timer = Registers::<TIMER_BLOCK>::new(Mapping::buffer(TIMER_BLOCK::LENGTH));
+        timer.mapping().write(0x8, AccessWidth::Bits32, 0x1234).unwrap();
+        assert_eq!(timer.read::<TIMER_COUNT>().unwrap(), Bits::new::<0x1234>());
+
+        // an enum has one variant per value
+        let config = r#"
+completion-metadata: {root: registers}
+registers:
+  UART:
+    CTRL: {offset: 0x10}
+    mode: {parent: UART.CTRL, bits: "2:1", values: "off=0, both=3, type=3"}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(rust_module(&map, "dup", "crate").unwrap_err(),
+                   vec!["base not found for UART", "UART.mode: value type=3 is the same as both, an enum cannot give both"]);
+    }
+
+    #[test]
+    fn test_doc() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x40000000
+length: 0x2000
+registers:
+  UART:
+    CTRL: {offset: 0x10, reset: 0x3, description: "Control <main>"}
+    mode: {parent: UART.CTRL, bits: "2:1", values: "off=0, tx=1, rx=2, both=3"}
+    enable: {parent: UART.CTRL, bits: "0:0", description: "a|b"}
+    DATA: {offset: 0x18, width: 8, read-write: rc}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+
+        let md = doc::markdown(&map, "test-chip");
+        for line in ["| UART.CTRL | 0x0010 | 0x40000010 | 32 | unspecified | 0x00000003 | Control &lt;main> |",
+                     "| 31:3 | 2:1 | 0 |\n|---|---|---|\n| - | mode | enable |",
+                     "| UART.mode | 2:1 | unspecified | 0x1 | off=0b00, tx=0b01, rx=0b10, both=0b11 |  |",
+                     "| UART.enable | 0 | unspecified | 0x1 |  | a\\|b |",
+                     "| 7:0 |\n|---|\n| DATA |"] {
+            assert!(md.contains(line), "missing {line}");
+        }
+
+        let html = doc::html(&map, "test-chip");
+        for line in ["<h3 id=\"UART_CTRL\">UART.CTRL</h3>", "<td>Control &lt;main&gt;</td>",
+                     "<td class=\"gap\" colspan=\"29\"></td><td class=\"field\" colspan=\"2\">mode</td>",
+                     "<th>7</th><th>6</th>", "off=0b00<br>tx=0b01"] {
+            assert!(html.contains(line), "missing {line}");
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = include_str!("../tools/data/testconfig.yaml");
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+
+        let report = validate(&doc, &mut *descender, None, false);
+        assert_eq!(report.errors, Vec::<String>::new());
+        assert_eq!(report.warnings, Vec::<String>::new());
+        assert!(report.count > 0);
+        assert!(!report.failed(true));
+
+        let report = validate(&doc, &mut *descender, Some("missing_parent"), false);
+        assert!(report.errors[0].contains("not found"));
+
+        let report = validate(&doc, &mut *descender, Some("other"), false);
+        assert_eq!(report.count, 8);
+        for expected in ["invalid read-write 'foo' for GPIO.pins[0].set", "shadow 'GPIO.words.set0' not found for GPIO.pins[0].set",
+                         "parent GPIO.pins[500].set not found for GPIO.pins[1].badindex", "offset not specified for GPIO.pins[2].nooffset",
+                         "invalid offset 'foo' for GPIO.pins[3].badoffset", "invalid bits specification '0' for GPIO.pins[5].badbits2"] {
+            assert!(report.errors.iter().any(|e| e == expected), "missing {expected}");
+        }
+        assert_eq!(report.errors.iter().filter(|e| e.starts_with("invalid bits")).count(), 4);
+
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x40000000
+length: 0x100
+registers:
+  UART:
+    CTRL: {offset: 0x10, read-write: rw, width: 32}
+    mode: {parent: UART.CTRL, bits: "2:1", values: "off=0, on=2"}
+    speed: {parent: UART.CTRL, bits: "3:2"}
+    flag: {parent: UART.CTRL, bits: "8:8", values: "on=2"}
+    low: {parent: UART.mode, bits: "1:1", values: "set=1"}
+    DATA: {offset: 0x100, read-write: rw, width: 16, shadow: UART.CTRL}
+    LATCH: {offset: 0x10, read-write: ro, width: 32, shadow: UART.DATA}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let report = validate(&doc, &mut *descender, None, false);
+        assert_eq!(report.errors, vec!["value on=2 does not fit the bits of UART.flag",
+                                       "offset 0x100 + 2 bytes of UART.DATA is beyond length 0x100",
+                                       "shadow offset 0x100 + 4 bytes of UART.LATCH is beyond length 0x100"]);
+        assert_eq!(report.warnings, vec!["bits 2:1 of UART.mode overlap bits 3:2 of UART.speed in UART.CTRL"]);
+    }
+
+    #[test]
+    fn test_address_space() {
+        let config = r#"
+completion-metadata: {root: registers}
+registers:
+  GPIO:
+    words:
+      function2: {offset: 0x08, read-write: rw}
+      level0: {offset: 0x34, read-write: ro, width: 16}
+      level1: {offset: 0x38, read-write: ro, bits: "3:0"}
+      copy: {offset: 0x08, read-write: rw}
+      byte: {offset: 0x0B, read-write: rw, width: 8}
+    pins:
+      - function: {parent: GPIO.words.function2, bits: "2:0"}
+        set: {offset: 0x1C, read-write: wo, bits: "0:0", shadow: GPIO.words.level0}
+        clear: {offset: 0x28, read-write: wo, bits: "5:5", shadow: GPIO.words.level1}
+      - function: {parent: GPIO.words.function2, bits: "4:2"}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+
+        let findings = AddressSpace::new(&map.registers, allowed_aliases(&doc)).findings();
+        let messages = |severity: Severity| -> Vec<String> {
+            findings.iter().filter(|f| f.severity == severity).map(|f| f.message.clone()).collect()
+        };
+        assert_eq!(messages(Severity::Warning), vec![
+            "bits 2:0 of GPIO.pins[0].function overlap bits 4:2 of GPIO.pins[1].function in GPIO.words.function2",
+            "GPIO.words.function2 and GPIO.words.copy share offset 0x8",
+            "bytes 0xB-0xB of GPIO.words.function2 overlap GPIO.words.byte",
+            "bytes 0xB-0xB of GPIO.words.copy overlap GPIO.words.byte"]);
+        assert_eq!(messages(Severity::Error), vec![
+            "shadow GPIO.words.level0 of GPIO.pins[0].set is 16 bits wide, GPIO.pins[0].set is 32 bits",
+            "shadow GPIO.words.level1 of GPIO.pins[0].clear does not hold its bits 5:5, only 3:0"]);
+        assert_eq!(messages(Severity::Note)[0], "gap 0xC-0x1B in GPIO between GPIO.words.function2 and GPIO.pins[0].set");
+
+        let allowed = format!("aliases: [GPIO.pins, GPIO.words.copy, GPIO.word]\n{config}");
+        let doc = load_document(&allowed).unwrap();
+        let findings = AddressSpace::new(&map.registers, allowed_aliases(&doc)).findings();
+        let warnings: Vec<&str> = findings.iter().filter(|f| f.severity == Severity::Warning).map(|f| f.message.as_str()).collect();
+        assert_eq!(warnings, vec!["bytes 0xB-0xB of GPIO.words.function2 overlap GPIO.words.byte"]);
+        assert_eq!(findings.iter().filter(|f| f.severity == Severity::Error).count(), 2);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x40000000
+length: 0x1000
+registers:
+  GPIO:
+    base: 0x7E200000
+    length: 0x100
+    device: /dev/gpiomem
+    level: {offset: 0x34, read-write: ro}
+    select: {offset: 0x0}
+  PWM:
+    base: 0x7E20C000
+    control: {offset: 0x0}
+  SPI:
+    fifo: {offset: 0x4}
+"#;
+        let mut register_tool = RegisterTool::new(descender_from_str(config).unwrap()) ;
+        register_tool.gather_regs(&vec!["GPIO.select=5", "PWM.control=7", "GPIO.select", "PWM.control", "SPI.fifo"]).unwrap();
+        let ranges = register_tool.physical_ranges();
+        assert_eq!(ranges, vec![("/dev/gpiomem", 0x7E200000, 0x7E200000 + page_size()),
+                                ("/dev/mem", 0x7E20C000, 0x7E20C000 + page_size()),
+                                ("/dev/mem", 0x40000000, 0x40000000 + page_size())]);
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_, v| Ok(v)).unwrap();
+        let values: Vec<u64> = replies.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(values, vec![5, 7, 5, 7, 0]);
+
+        let config_beyond = config.replace("level: {offset: 0x34", "level: {offset: 0x100");
+        let mut register_tool = RegisterTool::new(descender_from_str(&config_beyond).unwrap()) ;
+        let errs = register_tool.gather_regs(&vec!["GPIO.level"]).unwrap_err();
+        assert_eq!(errs[0], "Register GPIO.level at offset 0x100 + 4 bytes is beyond the mapped length 0x100");
+
+        // a field is mapped with the block of the word holding it, not its own
+        let config_field = config.replace("    fifo: {offset: 0x4}", "    fifo: {offset: 0x4}\n    gpio_bit: {parent: GPIO.select, bits: \"3:3\"}");
+        let mut register_tool = RegisterTool::new(descender_from_str(&config_field).unwrap()) ;
+        register_tool.gather_regs(&vec!["SPI.gpio_bit"]).unwrap();
+        assert_eq!(register_tool.physical_ranges(), vec![("/dev/gpiomem", 0x7E200000, 0x7E200000 + page_size())]);
+        let root = &load_document(&config_field).unwrap()["registers"];
+        assert_eq!(parent_chain("SPI.gpio_bit", |p| parent_in(root, p)).unwrap(), vec!["SPI.gpio_bit", "GPIO.select"]);
+        let looped = |p: &str| Some(if p == "A" { "B" } else { "A" }.to_string());
+        assert_eq!(parent_chain("A", looped).unwrap_err(), "parent loop at A for A");
+        assert_eq!(word_of("A", looped), "A");
+
+        // a file whose blocks all give their own base and length needs none of its own
+        let own_regions = config.replace("base: 0x40000000\nlength: 0x1000\n", "")
+            .replace("  SPI:\n    fifo: {offset: 0x4}\n", "")
+            .replace("    base: 0x7E20C000\n", "    base: 0x7E20C000\n    length: 0x1000\n");
+        let mut register_tool = RegisterTool::new(descender_from_str(&own_regions).unwrap()) ;
+        register_tool.gather_regs(&vec!["GPIO.select=5", "PWM.control"]).unwrap();
+        assert_eq!(register_tool.physical_ranges()[1], ("/dev/mem", 0x7E20C000, 0x7E20C000 + page_size()));
+        let mut register_tool = RegisterTool::new(descender_from_str(&own_regions.replace("    base: 0x7E20C000\n", "")).unwrap()) ;
+        assert_eq!(register_tool.gather_regs(&vec!["PWM.control"]).unwrap_err(), vec!["base not found for PWM"]);
+
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(map.regions.len(), 2);
+        assert_eq!(map.region("PWM"), Region { device: "/dev/mem".to_string(), base: 0x7E20C000, length: 0x1000 });
+        assert_eq!(map.region("SPI").base, 0x40000000);
+        let header = c_header(&map, "bcm", false).unwrap();
+        assert!(header.contains("#define GPIO_BASE 0x7E200000u"));
+        assert!(header.contains("#define PWM_LENGTH 0x1000u"));
+        let svd = export_svd(&map, "bcm");
+        assert!(svd.contains("<baseAddress>0x7E20C000</baseAddress>"));
+
+        // imported blocks keep their own bases with offsets relative to them
+        let imported = svd_to_document(&svd).unwrap();
+        assert_eq!(imported["registers"]["PWM"]["base"].as_i64(), Some(0x7E20C000));
+        assert_eq!(imported["registers"]["GPIO"]["level"]["offset"].as_i64(), Some(0x34));
+
+        // the registers at offset 0 of GPIO and PWM are in different memory
+        let mut descender = descender_from_str(config).unwrap();
+        let report = validate(&doc, &mut *descender, None, false);
+        assert!(report.errors.is_empty(), "{report:?}");
+        assert!(!report.warnings.iter().any(|w| w.contains("share offset")), "{report:?}");
+
+        let bad = config.replace("base: 0x7E20C000", "base: nowhere");
+        let doc = load_document(&bad).unwrap();
+        let mut descender = descender_from_str(&bad).unwrap();
+        let report = validate(&doc, &mut *descender, None, false);
+        assert_eq!(report.errors, vec!["invalid base 'nowhere' for block PWM"]);
+    }
+
+    #[test]
+    fn test_compose() {
+        let dir = std::env::temp_dir().join(format!("register_tool_compose_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("soc")).unwrap();
+        let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+        write("soc/bcm.yaml", r#"
+include: gpio.yaml
+completion-metadata: {root: registers}
+device: /dev/gpiomem
+base: 0x7E200000
+length: 0x100
+aliases: [GPIO.copy]
+"#);
+        write("soc/gpio.yaml", r#"
+registers:
+  GPIO:
+    set0: {offset: 0x1C, read-write: wo, shadow: GPIO.level0}
+    level0: {offset: 0x34, read-write: ro}
+    copy: {offset: 0x34, read-write: ro}
+    led: {parent: GPIO.set0, bits: "27:27"}
+"#);
+        write("common.yaml", "completion-metadata: {root: registers}\ndescription: board\n");
+        write("board.yaml", r#"
+include: [common.yaml]
+import: {soc: soc/bcm.yaml}
+registers:
+  soc:
+    GPIO:
+      level0: {description: "Pin levels"}
+  LED_STATUS: {parent: soc.GPIO.set0, bits: "27:27"}
+"#);
+        let board = dir.join("board.yaml");
+        let board = board.to_str().unwrap();
+        let (doc, mut descender) = load_config(board).unwrap();
+        assert_eq!(doc["description"].as_str(), Some("board"));
+        assert!(doc["include"].is_badvalue() && doc["import"].is_badvalue());
+        assert_eq!(doc["registers"]["soc"]["base"].as_i64(), Some(0x7E200000));
+        assert_eq!(doc["registers"]["soc"]["GPIO"]["led"]["parent"].as_str(), Some("soc.GPIO.set0"));
+        assert_eq!(allowed_aliases(&doc), vec!["soc.GPIO.copy"]);
+
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(map.get("LED_STATUS").unwrap().word, "soc.GPIO.set0");
+        assert_eq!(map.get("soc.GPIO.set0").unwrap().shadow.as_deref(), Some("soc.GPIO.level0"));
+        assert_eq!(map.get("soc.GPIO.level0").unwrap().description.as_deref(), Some("Pin levels"));
+        assert_eq!(map.region("soc").device, "/dev/gpiomem");
+        assert_eq!(composed_files(board).unwrap().len(), 3);
+
+        let mut register_tool = RegisterTool::new(load_descender(board).unwrap()) ;
+        register_tool.gather_regs(&vec!["LED_STATUS=1", "soc.GPIO.led"]).unwrap();
+        assert_eq!(register_tool.physical_ranges(), vec![("/dev/gpiomem", 0x7E200000, 0x7E200000 + page_size())]);
+
+        // an SoC file whose blocks give their own memory keeps it under the namespace
+        write("soc/bcm2711.yaml", r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+registers:
+  GPIO:
+    device: /dev/gpiomem
+    base: 0x7E200000
+    length: 0x100
+    set0: {offset: 0x1C, read-write: wo, shadow: GPIO.level0}
+    level0: {offset: 0x34, read-write: ro}
+  PWM:
+    base: 0x7E20C000
+    length: 0x28
+    control: {offset: 0x0}
+"#);
+        write("pi.yaml", r#"
+completion-metadata: {root: registers}
+import: {soc: soc/bcm2711.yaml}
+registers:
+  LED_STATUS: {parent: soc.GPIO.set0, bits: "27:27"}
+"#);
+        let pi = dir.join("pi.yaml");
+        let pi = pi.to_str().unwrap();
+        let (doc, mut descender) = load_config(pi).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        assert_eq!(map.blocks(), vec!["LED_STATUS", "soc.GPIO", "soc.PWM"]);
+        assert_eq!(map.block("soc.PWM.control"), "soc.PWM");
+        assert_eq!(map.region("soc.GPIO"), Region { device: "/dev/gpiomem".into(), base: 0x7E200000, length: 0x100 });
+        assert_eq!(map.region("soc.PWM"), Region { device: "/dev/mem".into(), base: 0x7E20C000, length: 0x28 });
+        assert!(export_svd(&map, "pi").contains("<name>soc_PWM</name>"));
+        let report = validate(&doc, &mut *descender, None, false);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        let mut register_tool = RegisterTool::new(load_descender(pi).unwrap()) ;
+        register_tool.gather_regs(&vec!["LED_STATUS=1", "soc.PWM.control"]).unwrap();
+        assert_eq!(register_tool.physical_ranges(), vec![("/dev/gpiomem", 0x7E200000, 0x7E200000 + page_size()),
+                                                         ("/dev/mem", 0x7E20C000, 0x7E20C000 + page_size())]);
+
+        write("a.yaml", "include: b.yaml\n");
+        write("b.yaml", "include: a.yaml\n");
+        let err = load_composed(dir.join("a.yaml").to_str().unwrap()).unwrap_err();
+        assert!(err.starts_with("include cycle: ") && err.contains("b.yaml -> ") && err.ends_with("a.yaml"), "{err}");
+        write("c.yaml", "import: {soc: soc/missing.yaml}\n");
+        let err = load_composed(dir.join("c.yaml").to_str().unwrap()).unwrap_err();
+        assert!(err.contains("c.yaml -> ") && err.contains("missing.yaml: No such file"), "{err}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_arrays() {
+        // the array generates the 58 pins the expanded example writes out by hand
+        let example = include_str!("../examples/raspberrypi4b_arrays.yaml");
+        let generated = load_document(example).unwrap();
+        let expanded = load_document(include_str!("../examples/raspberrypi4b.yaml")).unwrap();
+        assert_eq!(expanded["registers"]["GPIO"]["pins"].as_vec().map(|pins| pins.len()), Some(58));
+        assert_eq!(generated["registers"]["GPIO"]["pins"], expanded["registers"]["GPIO"]["pins"]);
+        assert_eq!(generated, expanded);
+
+        let mut register_tool = RegisterTool::new(descender_from_str(example).unwrap()) ;
+        register_tool.gather_regs(&vec!["GPIO.pins[27].function=output", "GPIO.pins[27].function", "GPIO.words.function2",
+                                        "GPIO.pins[57].pull_up_down=up", "GPIO.words.pull_up_down3"]).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.apply_registers(|_reg, v| Ok(v)).unwrap();
+        let values: Vec<u64> = replies.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(values, vec![1, 1, 1 << 21, 1, 1 << 18]);
+
+        let config = r#"
+registers:
+  UART:
+    array:
+      count: 2
+      element:
+        data: {offset: "{0x100 * i}", description: "UART{i} data"}
+        flags: {offset: "{0x100 * i + 0x18}", bits: "3:0", stride: 4, per-word: 2, parent: "x{word}.{slot}"}
+        lanes: {array: {count: 2, element: {offset: "{i * 4}"}}}
+"#;
+        let doc = load_document(config).unwrap();
+        let uart = &doc["registers"]["UART"];
+        assert_eq!(uart[1]["data"]["offset"].as_i64(), Some(0x100));
+        assert_eq!(uart[1]["data"]["description"].as_str(), Some("UART1 data"));
+        assert_eq!(uart[1]["flags"]["bits"].as_str(), Some("7:4"));
+        assert_eq!(uart[1]["flags"]["parent"].as_str(), Some("x0.1"));
+        assert!(uart[1]["flags"]["stride"].is_badvalue());
+        assert_eq!(uart[1]["lanes"][1]["offset"].as_i64(), Some(4));
+
+        for (offset, error) in [("{j}", "unknown 'j'"), ("{word}", "word needs a stride"),
+                                ("{4 / (i - i)}", "4 / 0 is out of range"), ("{(1 + 2}", "missing )")] {
+            let config = format!("registers: {{UART: {{array: {{count: 1, element: {{r: {{offset: \"{offset}\"}}}}}}}}}}");
+            assert_eq!(load_document(&config).unwrap_err(), format!("registers.UART[0].r.offset: {error}"));
+        }
+    }
+
+    #[test]
+    fn test_output_formats() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x7E200000
+length: 0x100
+registers:
+  GPIO:
+    select: {offset: 0x8, bits: "23:21", read-write: rw, values: "input=0, output=1"}
+    level: {offset: 0x34, bits: "7:4", read-write: ro}
+"#;
+        let mut register_tool = RegisterTool::new(descender_from_str(config).unwrap()) ;
+        register_tool.gather_regs(&vec!["GPIO.select=output", "GPIO.level"]).unwrap();
+        register_tool.set_test_area() ;
+        let lines = |format: OutputFormat| -> Vec<String> {
+            let lines = std::cell::RefCell::new(Vec::new());
+            register_tool.access_registers(|reg, access| {
+                lines.borrow_mut().push(format.format(reg, access));
+                Ok(access.value)
+            }).unwrap();
+            lines.into_inner()
+        };
+        assert_eq!(lines(OutputFormat::Hex), vec!["GPIO.select = 0x1 (output)", "GPIO.level = 0x0"]);
+        assert_eq!(lines(OutputFormat::Binary)[0], "GPIO.select = 0b001 (output)");
+        assert_eq!(lines(OutputFormat::Json)[0],
+                   r#"{"path":"GPIO.select","address":2116026376,"word":2097152,"value":1,"bits":"23:21","access":"rw","label":"output"}"#);
+        assert_eq!(lines(OutputFormat::Csv)[1], "GPIO.level,0x7E200034,0x00000000,0,7:4,ro,");
+        assert_eq!(lines(OutputFormat::Diagram)[0].lines().nth(3), Some("            ^^^"));
+        assert_eq!(OutputFormat::Csv.header(), Some("path,address,word,value,bits,access,label"));
+        assert_eq!(signed(0b111, 3), -1);
+        assert_eq!(signed(0b011, 3), 3);
+        assert!(OutputFormat::parse("octal").is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x7E200000
+length: 0x100
+registers:
+  GPIO:
+    words:
+      function2: {offset: 0x8}
+      level0: {offset: 0x34, read-write: ro}
+    pins:
+      - {function: {parent: GPIO.words.function2, bits: "2:0", values: "input=0, output=1"}}
+      - {function: {parent: GPIO.words.function2, bits: "5:3", values: "input=0, output=1"}}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+
+        let (word, fields) = decode(&map, "GPIO.words.function2", 0b001_000).unwrap();
+        assert_eq!(word, "GPIO.words.function2");
+        assert_eq!(fields.iter().map(|f| (f.name.as_str(), f.value, f.label.as_deref())).collect::<Vec<_>>(),
+                   vec![("pins[1].function", 1, Some("output")), ("pins[0].function", 0, Some("input"))]);
+
+        // a field decodes the word holding it
+        let (word, _) = decode(&map, "GPIO.pins[0].function", 0).unwrap();
+        assert_eq!(word, "GPIO.words.function2");
+
+        let reg = &map.get("GPIO.words.function2").unwrap().op;
+        let table = decode_table(&word, reg, &Access { value: 8, word: 0b001_000, address: 0x7E200008 }, &fields);
+        assert_eq!(table, "GPIO.words.function2 = 0x00000008 at 0x7E200008, offset 0x8\n  \
+                           field             bits  value  label\n  \
+                           pins[1].function  5:3   0x1    output\n  \
+                           pins[0].function  2:0   0x0    input\n");
+        let (word, fields) = decode(&map, "GPIO.words.level0", 0).unwrap();
+        let reg = &map.get("GPIO.words.level0").unwrap().op;
+        assert!(decode_table(&word, reg, &Access { value: 0, word: 0, address: 0x7E200034 }, &fields).ends_with("no named fields\n"));
+        assert!(decode(&map, "GPIO.words.missing", 0).is_err());
+    }
+
+    #[test]
+    fn test_dump_all() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x7E200000
+length: 0x100
+registers:
+  GPIO:
+    words:
+      function0: {offset: 0x0}
+      set0: {offset: 0x1C, read-write: wo}
+      status: {offset: 0x20, read-write: rc}
+      latch: {offset: 0x24, read-write: wo, shadow: GPIO.words.function0}
+    pins:
+      - {function: {parent: GPIO.words.function0, bits: "2:0"}, set: {parent: GPIO.words.set0, bits: "0:0"}}
+  PWM:
+    control: {offset: 0x80}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+
+        let paths = |prefix: &str| map.words_under(prefix).iter().map(|w| w.path().to_string()).collect::<Vec<String>>();
+        assert_eq!(paths(""), vec!["GPIO.words.function0", "GPIO.words.set0", "GPIO.words.status",
+                                   "GPIO.words.latch", "PWM.control"]);
+        // the words of fields below the prefix, wherever they are
+        assert_eq!(paths("GPIO.pins[0]"), vec!["GPIO.words.function0", "GPIO.words.set0"]);
+        assert_eq!(paths("PWM"), vec!["PWM.control"]);
+        assert!(paths("PW").is_empty());
+
+        let hazards: Vec<Option<&str>> = map.words_under("GPIO.words").iter().map(|w| w.op.read_hazard(false)).collect();
+        assert_eq!(hazards, vec![None, Some("write-only"), Some("cleared by reading"), None]);
+        assert_eq!(map.get("GPIO.words.status").unwrap().op.read_hazard(true), None);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let config = r#"
+completion-metadata: {root: registers}
+device: /dev/mem
+base: 0x7E200000
+length: 0x100
+registers:
+  GPIO:
+    words:
+      function0: {offset: 0x0, read-write: rw}
+      level0: {offset: 0x34, read-write: ro}
+      events: {offset: 0x40, read-write: w1c}
+      set0: {offset: 0x1C, read-write: wo, shadow: GPIO.words.level0}
+      clear0: {offset: 0x28, read-write: wo, shadow: GPIO.words.level0}
+"#;
+        let doc = load_document(config).unwrap();
+        let mut descender = descender_from_str(config).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        let hash = config_hash(&doc).unwrap();
+        // the same map written differently hashes the same
+        let reformatted = config.replace("level0: {offset: 0x34, read-write: ro}", "level0:\n        offset: 52\n        read-write: ro");
+        assert_eq!(config_hash(&load_document(&reformatted).unwrap()).unwrap(), hash);
+
+        let snapshot = Snapshot {
+            hash: hash.clone(),
+            registers: vec![
+                SavedRegister { path: "GPIO.words.function0".to_string(), address: 0x7E200000, word: 0x249 },
+                SavedRegister { path: "GPIO.words.level0".to_string(), address: 0x7E200034, word: 0x1 },
+                SavedRegister { path: "GPIO.words.events".to_string(), address: 0x7E200040, word: 0x80000000 },
+                SavedRegister { path: "GPIO.words.set0".to_string(), address: 0x7E20001C, word: 0x1 },
+                SavedRegister { path: "GPIO.words.clear0".to_string(), address: 0x7E200028, word: 0x1 },
+            ],
+        };
+        assert_eq!(Snapshot::parse(&snapshot.to_yaml()).unwrap(), snapshot);
+        assert!(snapshot.check(&map, &hash).is_ok());
+
+        let (settings, skipped) = snapshot.restore_plan(&map);
+        assert_eq!(settings, vec!["GPIO.words.function0=0x249"]);
+        // the set/clear pair read their levels, writing them back would clear the pins just set
+        assert_eq!(skipped, vec![("GPIO.words.level0".to_string(), "read-only"),
+                                 ("GPIO.words.events".to_string(), "writing 1 clears"),
+                                 ("GPIO.words.set0".to_string(), "saved from its shadow"),
+                                 ("GPIO.words.clear0".to_string(), "saved from its shadow")]);
+        let mut register_tool = RegisterTool::new(descender_from_str(config).unwrap()) ;
+        let settings: Vec<&str> = settings.iter().map(|s| s.as_str()).collect();
+        register_tool.gather_regs(&settings).unwrap();
+        register_tool.set_test_area() ;
+        let replies = register_tool.access_registers(|_, access| Ok(access.word)).unwrap();
+        assert_eq!(replies[0], Ok(0x249));
+
+        // refused when the map or the address of a saved register has changed
+        let errs = snapshot.check(&map, "0").unwrap_err();
+        assert!(errs[0].starts_with("The register map has changed"));
+        let moved = config.replace("function0: {offset: 0x0", "function0: {offset: 0x4").replace("base: 0x7E200000", "base: 0x7E300000");
+        let doc = load_document(&moved).unwrap();
+        let mut descender = descender_from_str(&moved).unwrap();
+        let map = RegisterMap::new(&doc, &mut *descender, "").unwrap();
+        let errs = snapshot.check(&map, &config_hash(&doc).unwrap()).unwrap_err();
+        assert_eq!(errs.len(), 6);
+        assert_eq!(errs[1], "GPIO.words.function0 has moved from 0x7E200000 to 0x7E300004");
+        assert_eq!(errs[2], "GPIO.words.level0 has moved from 0x7E200034 to 0x7E300034");
+        assert!(Snapshot::parse("registers: []").is_err());
+    }
+
     #[test]
     fn test_bad_config() {
-        let bogus_base = r"{}" ;
+        let bogus_base = r"{completion-metadata: {root: registers}, registers: {GPIO: {level: {offset: 0x34}}}}" ;
         let descender = YamlDescender::new(bogus_base, true).unwrap() ;
-        let register_tool = RegisterTool::new(Box::new(descender)) ;
-        match register_tool {
-            Err(errs)    => assert_eq!(errs.len(), 3),
-            Ok(_) => panic!("Should have failed to create a register tool")
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+        match register_tool.gather_regs(&vec!["GPIO.level"]) {
+            Err(errs)    => assert_eq!(errs, vec!["base not found for GPIO"]),
+            Ok(_) => panic!("Should have failed to gather a register with no base")
         } ;
     }
 
     #[test]
     fn test_correct_basic() {
-        let correct = r"{device: /dev/mem, base: 0x40000000, length: 0x1000}" ;
+        let correct = r"{completion-metadata: {root: registers}, device: /dev/mem, base: 0x40000000, length: 0x1000, registers: {GPIO: {level: {offset: 0x34}}}}" ;
         let descender = YamlDescender::new(correct, true).unwrap() ;
-        let register_tool = RegisterTool::new(Box::new(descender)) ;
-        assert!(register_tool.is_ok()) ;
+        let mut register_tool = RegisterTool::new(Box::new(descender)) ;
+        register_tool.gather_regs(&vec!["GPIO.level"]).unwrap() ;
+        assert_eq!(register_tool.physical_ranges(), vec![("/dev/mem", 0x40000000, 0x40000000 + page_size())]) ;
     }
 }
\ No newline at end of file
@@ -22,81 +22,265 @@
 // SOFTWARE.
 // 
 use libc::{mmap, off_t, MAP_SHARED, PROT_READ, PROT_WRITE};
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::ptr;
 
-/// Maps a memory region using mmap system call
+/// The size of a page of memory, which mmap offsets must be a multiple of
+pub fn page_size() -> u64 {
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if size > 0 { size as u64 } else { 4096 }
+}
+
 ///
-/// # Safety
+/// The page aligned region covering length bytes at address, as its start, its length
+/// and where address lies within it
 ///
-/// This function is unsafe because it performs system memory mapping.
-/// The caller must ensure that:
-/// - The address and length are valid for memory mapping
-/// - The resulting mapped memory is accessed properly
-/// - The mapping is properly unmapped when no longer needed
-pub fn mmap_memory(device: &str, address: u64, length: u64) -> Result<*mut u8, String> { 
+pub fn page_span(address: u64, length: u64, page_size: u64) -> (u64, u64, u64) {
+    let start = address - address % page_size;
+    let delta = address - start;
+    let span = (length + delta).div_ceil(page_size) * page_size;
+    (start, span, delta)
+}
+
+///
+/// errno of the last failed call, and its text
+///
+fn last_error() -> (i32, String) {
     unsafe {
-        let fd = libc::open(device.as_ptr() as *const libc::c_char, libc::O_RDWR) ;
-        if fd < 0 {
-            return Err(format!("Failed to open {}", device));
+        #[cfg(target_os = "macos")]
+        let errno = *libc::__error();
+        #[cfg(not(target_os = "macos"))]
+        let errno = *libc::__errno_location();
+        let text = CStr::from_ptr(libc::strerror(errno))
+            .to_str()
+            .unwrap_or("Invalid error message")
+            .to_string();
+        (errno, text)
+    }
+}
+
+///
+/// Add what an operator can do about errno, when opening or mapping device fails, to
+/// message
+///
+fn with_hint(message: String, device: &str, errno: i32) -> String {
+    let hint = match errno {
+        libc::EACCES | libc::EPERM if device == "/dev/mem" =>
+            "try /dev/gpiomem, or run as root on a kernel that allows /dev/mem",
+        libc::EACCES | libc::EPERM =>
+            "run as root, or add the user to the group owning the device",
+        libc::ENOENT if device == "/dev/gpiomem" =>
+            "the gpiomem driver is not loaded on this board, try /dev/mem as root",
+        libc::ENOENT => "check the 'device' of the register file",
+        libc::EBUSY => "the device is held by another driver or process",
+        libc::EINVAL | libc::ENXIO => "check 'base' and 'length' lie within the device",
+        _ => return message,
+    } ;
+    format!("{message}, {hint}")
+}
+
+///
+/// What the memory of a Mapping belongs to
+///
+enum Backing {
+    /// pages of a device mapped with mmap, unmapped on drop
+    Device { pages: *mut libc::c_void, span: usize },
+    /// memory standing in for the registers in test mode, allocated as u64 so that 64
+    /// bit registers are aligned
+    Buffer(#[allow(dead_code)] Vec<u64>),
+}
+
+///
+/// Register memory, mapped from a device or allocated for testing, that is released
+/// when dropped
+///
+/// The registers are only reached through read and write, which check every access
+/// against the bounds of the mapping and the alignment of the register.
+///
+pub struct Mapping {
+    backing: Backing,
+    /// the first register, within the pages for a device
+    addr: *mut u8,
+    length: u64,
+}
+
+impl Mapping {
+    ///
+    /// Map length bytes of device at address.  The address need not be page aligned,
+    /// the pages around it are mapped and offsets remain relative to address.
+    ///
+    pub fn device(device: &str, address: u64, length: u64) -> Result<Mapping, String> {
+        let (start, span, delta) = page_span(address, length, page_size());
+        let path = CString::new(device).map_err(|_| format!("Device path {device:?} contains a NUL byte"))?;
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_RDWR) ;
+            if fd < 0 {
+                let (errno, text) = last_error();
+                return Err(with_hint(format!("Failed to open {device}: {text} (errno: {errno})"), device, errno));
+            }
+
+            let pages = mmap(
+                ptr::null_mut(),
+                span as usize,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED,
+                fd,
+                start as off_t
+            );
+            let (errno, text) = last_error();
+
+            // the mapping holds its own reference to the device
+            libc::close(fd);
+
+            if pages == libc::MAP_FAILED {
+                return Err(with_hint(format!("Memory mapping of {device} at 0x{start:X} failed: {text} (errno: {errno})"),
+                                     device, errno));
+            }
+            Ok(Mapping {
+                backing: Backing::Device { pages, span: span as usize },
+                addr: (pages as *mut u8).add(delta as usize),
+                length,
+            })
+        }
+    }
+
+    /// zeroed memory of length bytes in place of a device, for test mode
+    pub fn buffer(length: u64) -> Mapping {
+        let mut memory = vec![0u64; length.div_ceil(8) as usize];
+        let addr = memory.as_mut_ptr() as *mut u8;
+        Mapping { backing: Backing::Buffer(memory), addr, length }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// read the register of width at offset
+    pub fn read(&self, offset: u64, width: AccessWidth) -> Result<u64, String> {
+        unsafe { mmio_read(self.addr, self.length, offset, width) }
+    }
+
+    /// write value to the register of width at offset
+    pub fn write(&self, offset: u64, width: AccessWidth, value: u64) -> Result<(), String> {
+        unsafe { mmio_write(self.addr, self.length, offset, width, value) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        match &self.backing {
+            Backing::Device { pages, span } => unsafe {
+                libc::munmap(*pages, *span);
+            },
+            // freed with the Vec
+            Backing::Buffer(_) => {}
+        }
+    }
+}
+
+///
+/// Size of the load/store used to access a register, from its 'width'
+///
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AccessWidth {
+    Bits8,
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+impl AccessWidth {
+    pub fn from_bits(width: u64) -> Result<AccessWidth, String> {
+        match width {
+            8 => Ok(AccessWidth::Bits8),
+            16 => Ok(AccessWidth::Bits16),
+            32 => Ok(AccessWidth::Bits32),
+            64 => Ok(AccessWidth::Bits64),
+            _ => Err(format!("Invalid width {width}, must be 8, 16, 32 or 64")),
         }
-    
-        let addr = mmap(
-            0 as *mut libc::c_void,
-            length as usize,
-            PROT_READ | PROT_WRITE,
-            MAP_SHARED,
-            fd,
-            address as off_t
-        );
-        
-        if addr == libc::MAP_FAILED {
-            #[cfg(target_os = "macos")]
-            let errno = *libc::__error();
-            #[cfg(not(target_os = "macos"))]
-            let errno = *libc::__errno_location();
-            let err_msg =  CStr::from_ptr(libc::strerror(errno))
-                .to_str()
-                .unwrap_or("Invalid error message");
-            return Err(format!("Memory mapping failed: {err_msg} (errno: {errno})"));
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            AccessWidth::Bits8 => 8,
+            AccessWidth::Bits16 => 16,
+            AccessWidth::Bits32 => 32,
+            AccessWidth::Bits64 => 64,
         }
-        Ok(addr as *mut u8)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        (self.bits() / 8) as u64
     }
 }
 
-/// Reads a 32-bit word from the specified memory address
+
+
 ///
-/// # Safety
+/// Checks an access of width at offset within a mapping of length bytes at base and
+/// returns the address to use
 ///
-/// This function is unsafe because it performs raw memory access.
-/// The caller must ensure that:
-/// - The memory address is properly aligned for 32-bit access
-/// - The memory address points to valid readable memory
-/// - No other threads are concurrently accessing this memory location
-pub fn read_word(address: usize) -> Result<u32, &'static str> {
-    if address % 4 != 0 {
-        return Err("Memory address must be 4-byte aligned");
+fn mmio_address(base: *mut u8, length: u64, offset: u64, width: AccessWidth) -> Result<*mut u8, String> {
+    if base.is_null() {
+        return Err("Register memory is not mapped".to_string());
     }
 
-    unsafe {Ok(ptr::read_volatile(address as *const u32))}
+    match offset.checked_add(width.bytes()) {
+        Some(end) if end <= length => {}
+        _ => return Err(format!("Register offset 0x{offset:X} + {} bytes is beyond mapped length 0x{length:X}", width.bytes())),
+    }
+
+    let address = base as usize + offset as usize;
+    if !address.is_multiple_of(width.bytes() as usize) {
+        return Err(format!("Register address 0x{address:X} must be {}-byte aligned", width.bytes()));
+    }
+
+    Ok(address as *mut u8)
 }
 
-/// Writes a 32-bit word to the specified memory address
+/// Reads a register of the given width at offset in a mapping of length bytes
+///
+/// All register reads go through here.  The access is checked against the mapping's
+/// bounds and the register's alignment before a single volatile load is made.
 ///
 /// # Safety
 ///
-/// This function is unsafe because it performs raw memory access.
-/// The caller must ensure that:
-/// - The memory address is properly aligned for 32-bit access
-/// - The memory address points to valid writable memory
-/// - No other threads are concurrently accessing this memory location
-pub fn write_word(address: usize, value: u32) -> Result<(), &'static str> {
-    if address % 4 != 0 {
-        return Err("Memory address must be 4-byte aligned");
+/// The caller must ensure base is null or a live mapping of at least length bytes
+unsafe fn mmio_read(base: *mut u8, length: u64, offset: u64, width: AccessWidth) -> Result<u64, String> {
+    let address = mmio_address(base, length, offset, width)?;
+
+    unsafe {
+        Ok(match width {
+            AccessWidth::Bits8 => ptr::read_volatile(address) as u64,
+            AccessWidth::Bits16 => ptr::read_volatile(address as *const u16) as u64,
+            AccessWidth::Bits32 => ptr::read_volatile(address as *const u32) as u64,
+            AccessWidth::Bits64 => ptr::read_volatile(address as *const u64),
+        })
     }
+}
+
+/// Writes a register of the given width at offset in a mapping of length bytes
+///
+/// All register writes go through here.  The access is checked against the mapping's
+/// bounds and the register's alignment before a single volatile store is made.
+///
+/// # Safety
+///
+/// The caller must ensure base is null or a live mapping of at least length bytes
+unsafe fn mmio_write(base: *mut u8, length: u64, offset: u64, width: AccessWidth, value: u64) -> Result<(), String> {
+    let address = mmio_address(base, length, offset, width)?;
 
     unsafe {
-        ptr::write_volatile(address as *mut u32, value);
-        Ok(())
+        match width {
+            AccessWidth::Bits8 => ptr::write_volatile(address, value as u8),
+            AccessWidth::Bits16 => ptr::write_volatile(address as *mut u16, value as u16),
+            AccessWidth::Bits32 => ptr::write_volatile(address as *mut u32, value as u32),
+            AccessWidth::Bits64 => ptr::write_volatile(address as *mut u64, value),
+        }
     }
-}
\ No newline at end of file
+    Ok(())
+}
@@ -0,0 +1,317 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+//!
+//! Checks of a whole register file, gathering every error and warning in it where the
+//! tool stops at the registers it is asked for.
+//!
+use std::io::Write;
+use aep_rust_common::descender::Descender;
+use yaml_rust::Yaml;
+use crate::address_space::{allowed_aliases, AddressSpace, Severity};
+use crate::compose::REGION_ENTRIES;
+use crate::register_map::{block_of, document_root, enclosing, enter_root, find_node, leave_root, parent_chain, parent_in, paths_below, region_block, resolve_def, word_of, RegisterDef};
+use crate::register_op::{parse_bits, parse_values, AccessWidth, RegisterAccess};
+
+///
+/// What validate found
+///
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// things worth knowing that are not wrong, e.g. gaps between registers
+    pub notes: Vec<String>,
+    /// the number of registers checked
+    pub count: usize,
+}
+
+impl Report {
+    pub fn failed(&self, warnings_as_errors: bool) -> bool {
+        !self.errors.is_empty() || (warnings_as_errors && !self.warnings.is_empty())
+    }
+}
+
+/// a scalar as written in the file
+fn show(value: &Yaml) -> String {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+///
+/// An entry of the register at path or, failing that, of the first of its parents to
+/// have it, as the descender finds it
+///
+fn inherited<'a>(root: &'a Yaml, path: &str, key: &str) -> Option<&'a Yaml> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut current = path.to_string();
+    loop {
+        let node = find_node(root, &current)?;
+        if !node[key].is_badvalue() {
+            return Some(&node[key]);
+        }
+        match node["parent"].as_str() {
+            Some(p) if !seen.iter().any(|s| s == p) => {
+                seen.push(current);
+                current = p.to_string();
+            }
+            _ => return None,
+        }
+    }
+}
+
+///
+/// The checks that need only the register's own entries and those it inherits,
+/// returning whether it is sound enough to resolve
+///
+fn check_entries(root: &Yaml, path: &str, length: Option<u64>, report: &mut Report) -> bool {
+    let errors = report.errors.len();
+    let node = &find_node(root, path).unwrap_or(&Yaml::BadValue);
+
+    if node["parent"].as_str().is_some() {
+        match parent_chain(path, |p| parent_in(root, p)) {
+            Ok(chain) => {
+                if let Some(missing) = chain.iter().skip(1).find(|p| find_node(root, p).is_none()) {
+                    report.errors.push(format!("parent {missing} not found for {path}"));
+                }
+            }
+            Err(e) => report.errors.push(e),
+        }
+    } else if !node["parent"].is_badvalue() {
+        report.errors.push(format!("invalid parent '{}' for {path}", show(&node["parent"])));
+    }
+
+    let width = match inherited(root, path, "width") {
+        None => None,
+        Some(Yaml::Integer(w)) => match AccessWidth::from_bits(*w as u64) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                report.errors.push(format!("invalid width specification '{w}' for {path}: {e}"));
+                None
+            }
+        },
+        Some(w) => {
+            report.errors.push(format!("invalid width specification '{}' for {path}", show(w)));
+            None
+        }
+    };
+    let width_bits = width.map(|w| w.bits()).unwrap_or(32);
+
+    let offset = match inherited(root, path, "offset") {
+        None => {
+            report.errors.push(format!("offset not specified for {path}"));
+            None
+        }
+        Some(Yaml::Integer(o)) if *o >= 0 => Some(*o as u64),
+        Some(o) => {
+            report.errors.push(format!("invalid offset '{}' for {path}", show(o)));
+            None
+        }
+    };
+
+    match inherited(root, path, "read-write") {
+        None => report.warnings.push(format!("read-write not specified for {path}")),
+        Some(Yaml::String(rw)) if RegisterAccess::parse(rw).is_some() => {}
+        Some(rw) => report.errors.push(format!("invalid read-write '{}' for {path}", show(rw))),
+    }
+
+    let bits = inherited(root, path, "bits");
+    if width.is_none() && bits.is_none() {
+        report.warnings.push(format!("width or bits not specified for {path}"));
+    }
+    let mut field_max = u64::MAX >> (64 - width_bits);
+    match bits {
+        None => {}
+        Some(Yaml::String(b)) => match parse_bits(b, width_bits) {
+            Ok((mask, shift)) => field_max = mask >> shift,
+            Err(e) => report.errors.push(format!("invalid bits specification '{b}' for {path}: {e}")),
+        },
+        Some(b) => report.errors.push(format!("invalid bits specification '{}' for {path}", show(b))),
+    }
+
+    match inherited(root, path, "values") {
+        None => {}
+        Some(Yaml::String(v)) => match parse_values(v) {
+            Ok(values) => {
+                for (name, value) in values.iter().filter(|(_, value)| *value > field_max) {
+                    report.errors.push(format!("value {name}={value} does not fit the bits of {path}"));
+                }
+            }
+            Err(e) => report.errors.push(format!("invalid values for {path}: {e}")),
+        },
+        Some(v) => report.errors.push(format!("invalid values '{}' for {path}", show(v))),
+    }
+
+    let mut accesses: Vec<(&str, u64)> = Vec::new();
+    if let Some(o) = offset {
+        accesses.push(("offset", o));
+    }
+    match inherited(root, path, "shadow") {
+        None => {}
+        Some(Yaml::String(shadow)) => match find_node(root, shadow) {
+            None => report.errors.push(format!("shadow '{shadow}' not found for {path}")),
+            Some(_) => match inherited(root, shadow, "offset") {
+                Some(Yaml::Integer(o)) if *o >= 0 => accesses.push(("shadow offset", *o as u64)),
+                _ => report.errors.push(format!("shadow '{shadow}' of {path} has no offset")),
+            },
+        },
+        Some(s) => report.errors.push(format!("invalid shadow '{}' for {path}", show(s))),
+    }
+    if let Some(length) = length {
+        let bytes = width.map(|w| w.bytes()).unwrap_or(4);
+        for (what, o) in accesses {
+            if o + bytes > length {
+                report.errors.push(format!("{what} 0x{o:X} + {bytes} bytes of {path} is beyond length 0x{length:X}"));
+            }
+        }
+    }
+
+    report.errors.len() == errors
+}
+
+/// does the block give any 'device', 'base' or 'length' of its own
+fn gives_region(root: &Yaml, block: &str) -> bool {
+    find_node(root, block).is_some_and(|node| REGION_ENTRIES.iter().any(|key| !node[*key].is_badvalue()))
+}
+
+///
+/// a block's own 'device', 'base' and 'length', those it doesn't give from the blocks
+/// enclosing it, the innermost first, and then the file
+///
+fn block_region<'a>(doc: &'a Yaml, root: &'a Yaml, block: &str) -> [&'a Yaml; 3] {
+    let mut nodes: Vec<&'a Yaml> = enclosing(block).chain([block]).filter_map(|b| find_node(root, b)).collect();
+    nodes.reverse();
+    REGION_ENTRIES.map(|key| nodes.iter().copied().map(|node| &node[key]).find(|v| !v.is_badvalue()).unwrap_or(&doc[key]))
+}
+
+/// the region of the register at path, that of the block its word is mapped from
+fn region_of<'a>(doc: &'a Yaml, root: &'a Yaml, path: &str) -> [&'a Yaml; 3] {
+    let word = word_of(path, |p| parent_in(root, p));
+    block_region(doc, root, region_block(&word, |b| gives_region(root, b)))
+}
+
+///
+/// The region entries of a top level block that the tool could not use
+///
+fn check_block(root: &Yaml, block: &str, report: &mut Report) {
+    let node = find_node(root, block).unwrap_or(&Yaml::BadValue);
+    match &node["device"] {
+        Yaml::BadValue | Yaml::String(_) => {}
+        d => report.errors.push(format!("invalid device '{}' for block {block}", show(d))),
+    }
+    for key in ["base", "length"] {
+        match &node[key] {
+            Yaml::BadValue => {}
+            Yaml::Integer(i) if *i >= 0 => {}
+            v => report.errors.push(format!("invalid {key} '{}' for block {block}", show(v))),
+        }
+    }
+}
+
+///
+/// Check every register under root, by default completion-metadata.root
+///
+/// Each register's entries are checked as the tool would read them, including those it
+/// inherits, and those without errors are then resolved just as RegisterOp does and
+/// placed in an AddressSpace to find overlaps, aliases, gaps and bad shadows.
+///
+pub fn validate(doc: &Yaml, descender: &mut dyn Descender<dyn Write>, root: Option<&str>, verbose: bool) -> Report {
+    let mut report = Report::default();
+    let root = match root {
+        Some(r) => r.to_string(),
+        None => document_root(doc),
+    };
+    let Some(root_node) = find_node(doc, &root) else {
+        report.errors.push(format!("root {root} not found"));
+        return report;
+    };
+    let paths = match paths_below(root_node, "") {
+        Ok(p) => p,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+    let mut blocks: Vec<&str> = Vec::new();
+    for path in &paths {
+        for block in [block_of(path), region_block(path, |b| gives_region(root_node, b))] {
+            if !blocks.contains(&block) {
+                check_block(root_node, block, &mut report);
+                blocks.push(block);
+            }
+        }
+    }
+
+    let old_root = match enter_root(descender, &root) {
+        Ok(r) => r,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+    let mut defs: Vec<RegisterDef> = Vec::new();
+    for path in paths {
+        if verbose {
+            println!("# checking {path}");
+        }
+        report.count += 1;
+        let length = region_of(doc, root_node, &path)[2].as_i64().map(|l| l as u64);
+        if !check_entries(root_node, &path, length, &mut report) {
+            continue;
+        }
+        match resolve_def(root_node, &*descender, &path) {
+            Ok(def) => defs.push(def),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    if let Err(e) = leave_root(descender, old_root) {
+        report.errors.push(e);
+    }
+
+    /*
+     * Blocks with their own region are separate address spaces, registers in them can
+     * only overlap those of blocks mapped from the same region
+     */
+    let mut spaces: Vec<([&Yaml; 3], Vec<RegisterDef>)> = Vec::new();
+    for def in defs {
+        let region = region_of(doc, root_node, def.path());
+        match spaces.iter_mut().find(|(r, _)| *r == region) {
+            Some((_, space)) => space.push(def),
+            None => spaces.push((region, vec![def])),
+        }
+    }
+    for (_, space) in &spaces {
+        for finding in AddressSpace::new(space, allowed_aliases(doc)).findings() {
+            match finding.severity {
+                Severity::Error => report.errors.push(finding.message),
+                Severity::Warning => report.warnings.push(finding.message),
+                Severity::Note => report.notes.push(finding.message),
+            }
+        }
+    }
+    report
+}
@@ -0,0 +1,74 @@
+// 
+// SPDX-License-Identifier: MIT
+// 
+// Copyright (c) 2025 Andrew Ellis Page
+// 
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+// 
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+// 
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+// 
+
+///
+/// Parse a number as given on the command line or in a register file.
+///
+/// Decimal, hex(`0x`), octal(`0o`) and binary(`0b`) are accepted and `_` may be used
+/// to separate digits, e.g. `0b0010_0000` or `0x7E20_0000`.  On failure the error
+/// names the offending character and its (1 based) position.
+///
+pub fn parse_value(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err("empty value".to_string());
+    }
+
+    let lower = trimmed.to_ascii_lowercase();
+    let (radix, kind, prefix_len) = if lower.starts_with("0x") {
+        (16, "hex", 2)
+    } else if lower.starts_with("0o") {
+        (8, "octal", 2)
+    } else if lower.starts_with("0b") {
+        (2, "binary", 2)
+    } else {
+        (10, "decimal", 0)
+    };
+
+    let lead = text.chars().count() - text.trim_start().chars().count();
+    let mut value: u64 = 0;
+    let mut digits = 0;
+    for (i, c) in trimmed.chars().enumerate().skip(prefix_len) {
+        if c == '_' {
+            continue;
+        }
+        let digit = match c.to_digit(radix) {
+            Some(d) => d as u64,
+            None => {
+                return Err(format!("invalid {kind} digit '{c}' at position {} in \"{text}\"", lead + i + 1));
+            }
+        };
+        value = match value.checked_mul(radix as u64).and_then(|v| v.checked_add(digit)) {
+            Some(v) => v,
+            None => return Err(format!("value \"{text}\" does not fit in 64 bits")),
+        };
+        digits += 1;
+    }
+
+    if digits == 0 {
+        return Err(format!("no {kind} digits in \"{text}\""));
+    }
+
+    Ok(value)
+}
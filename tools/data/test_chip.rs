@@ -0,0 +1,241 @@
+// Generated by register_tool from test_chip, do not edit
+#[allow(non_camel_case_types, dead_code)]
+pub mod test_chip {
+    use crate::pac::{Bits, Block, FieldValue, ReadClearable, Readable, Register, Writable};
+    use crate::register_op::{AccessWidth, RegisterAccess};
+
+    pub const DEVICE: &str = "/dev/mem";
+    pub const BASE: u64 = 0x40000000;
+    pub const LENGTH: u64 = 0x2000;
+
+    /// UART
+    pub struct UART_BLOCK;
+
+    impl Block for UART_BLOCK {
+        const DEVICE: &'static str = "/dev/mem";
+        const BASE: u64 = 0x40000000;
+        const LENGTH: u64 = 0x2000;
+    }
+
+    /// TIMER
+    pub struct TIMER_BLOCK;
+
+    impl Block for TIMER_BLOCK {
+        const DEVICE: &'static str = "/dev/mem";
+        const BASE: u64 = 0x40010000;
+        const LENGTH: u64 = 0x100;
+    }
+
+    /// UART.CTRL: control
+    pub struct UART_CTRL;
+
+    impl Register for UART_CTRL {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.CTRL";
+        const OFFSET: u64 = 0x10;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::Unspecified;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl Readable for UART_CTRL {}
+
+    impl Writable for UART_CTRL {}
+
+    /// UART.mode
+    pub struct UART_MODE;
+
+    impl Register for UART_MODE {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.mode";
+        const OFFSET: u64 = 0x10;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 1;
+        const MASK: u64 = 0x6;
+        const ACCESS: RegisterAccess = RegisterAccess::Unspecified;
+        const SHADOW: Option<u64> = None;
+        type Value = UART_MODE_VALUE;
+    }
+
+    impl Readable for UART_MODE {}
+
+    impl Writable for UART_MODE {}
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum UART_MODE_VALUE {
+        off,
+        tx,
+        rx,
+        type_,
+    }
+
+    impl FieldValue for UART_MODE_VALUE {
+        fn to_bits(self) -> u64 {
+            match self {
+                UART_MODE_VALUE::off => 0x0,
+                UART_MODE_VALUE::tx => 0x1,
+                UART_MODE_VALUE::rx => 0x2,
+                UART_MODE_VALUE::type_ => 0x3,
+            }
+        }
+
+        fn from_bits(bits: u64) -> Result<Self, String> {
+            match bits {
+                0x0 => Ok(UART_MODE_VALUE::off),
+                0x1 => Ok(UART_MODE_VALUE::tx),
+                0x2 => Ok(UART_MODE_VALUE::rx),
+                0x3 => Ok(UART_MODE_VALUE::type_),
+                _ => Err(format!("{} (0x{:X}) is not a value of UART.mode", bits, bits)),
+            }
+        }
+    }
+
+    /// UART.STATUS
+    pub struct UART_STATUS;
+
+    impl Register for UART_STATUS {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.STATUS";
+        const OFFSET: u64 = 0x14;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::ReadOnly;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl Readable for UART_STATUS {}
+
+    /// UART.level
+    pub struct UART_LEVEL;
+
+    impl Register for UART_LEVEL {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.level";
+        const OFFSET: u64 = 0x18;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::ReadOnly;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl Readable for UART_LEVEL {}
+
+    /// UART.set
+    pub struct UART_SET;
+
+    impl Register for UART_SET {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.set";
+        const OFFSET: u64 = 0x1C;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::WriteOnly;
+        const SHADOW: Option<u64> = Some(0x18);
+        type Value = Bits<32>;
+    }
+
+    impl Readable for UART_SET {}
+
+    impl Writable for UART_SET {}
+
+    /// UART.clear
+    pub struct UART_CLEAR;
+
+    impl Register for UART_CLEAR {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.clear";
+        const OFFSET: u64 = 0x20;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::Write1Clear;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl Readable for UART_CLEAR {}
+
+    impl Writable for UART_CLEAR {}
+
+    /// UART.STAMP
+    pub struct UART_STAMP;
+
+    impl Register for UART_STAMP {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.STAMP";
+        const OFFSET: u64 = 0x28;
+        const WIDTH: AccessWidth = AccessWidth::Bits64;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFFFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::Unspecified;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<64>;
+    }
+
+    impl Readable for UART_STAMP {}
+
+    impl Writable for UART_STAMP {}
+
+    /// UART.EVENTS
+    pub struct UART_EVENTS;
+
+    impl Register for UART_EVENTS {
+        type Block = UART_BLOCK;
+        const PATH: &'static str = "UART.EVENTS";
+        const OFFSET: u64 = 0x30;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::ReadClear;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl ReadClearable for UART_EVENTS {}
+
+    impl Writable for UART_EVENTS {}
+
+    /// TIMER.COUNT
+    pub struct TIMER_COUNT;
+
+    impl Register for TIMER_COUNT {
+        type Block = TIMER_BLOCK;
+        const PATH: &'static str = "TIMER.COUNT";
+        const OFFSET: u64 = 0x0;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::ReadClear;
+        const SHADOW: Option<u64> = Some(0x8);
+        type Value = Bits<32>;
+    }
+
+    impl Readable for TIMER_COUNT {}
+
+    impl Writable for TIMER_COUNT {}
+
+    /// TIMER.LATCH
+    pub struct TIMER_LATCH;
+
+    impl Register for TIMER_LATCH {
+        type Block = TIMER_BLOCK;
+        const PATH: &'static str = "TIMER.LATCH";
+        const OFFSET: u64 = 0x8;
+        const WIDTH: AccessWidth = AccessWidth::Bits32;
+        const SHIFT: u32 = 0;
+        const MASK: u64 = 0xFFFFFFFF;
+        const ACCESS: RegisterAccess = RegisterAccess::ReadOnly;
+        const SHADOW: Option<u64> = None;
+        type Value = Bits<32>;
+    }
+
+    impl Readable for TIMER_LATCH {}
+}